@@ -149,3 +149,18 @@ pub struct ListCommunities {
 pub struct ListCommunitiesResponse {
     pub communities: Vec<CommunityView>,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Block a community.
+pub struct BlockCommunity {
+    pub community_id: CommunityId,
+    pub block: bool,
+    pub auth: Sensitive<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// The response for blocking a community.
+pub struct BlockCommunityResponse {
+    pub community_view: CommunityView,
+    pub blocked: bool,
+}
@@ -10,8 +10,14 @@ pub enum ClientError {
     QueryString(#[from] serde_qs::Error),
     #[error("Json error {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error("Conversion error {0}")]
+    WasmBindgen(#[from] serde_wasm_bindgen::Error),
     #[error("HTTP Error")]
     HttpError,
     #[error("Must be authorized to use this API endpoint")]
     NotAuthorized,
+    #[error("Offline, this will be sent once you're back online")]
+    QueuedOffline,
+    #[error("{0}")]
+    Backend(String),
 }
@@ -7,18 +7,23 @@ use comment::{
     SaveComment,
 };
 use community::{
-    CommunityResponse, FollowCommunity, GetCommunity, ListCommunities, ListCommunitiesResponse,
+    BlockCommunity, BlockCommunityResponse, CommunityResponse, FollowCommunity, GetCommunity,
+    ListCommunities, ListCommunitiesResponse,
 };
 use error::ClientError;
 use log::info;
-use person::{GetPersonDetails, GetPersonDetailsResponse, Login, LoginResponse};
+use person::{
+    BlockPerson, BlockPersonResponse, GetPersonDetails, GetPersonDetailsResponse, Login,
+    LoginResponse, SaveUserSettings,
+};
 use post::{
-    CreatePostLike, GetPost, GetPostResponse, GetPosts, GetPostsResponse, PostResponse, SavePost,
+    CreatePostLike, GetPost, GetPostResponse, GetPosts, GetPostsResponse, MarkPostAsRead,
+    PostResponse, SavePost,
 };
 use sensitive::Sensitive;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_wasm_bindgen::to_value;
-use site::{GetSite, GetSiteResponse};
+use site::{GetSite, GetSiteResponse, ResolveObject, ResolveObjectResponse};
 use wasm_bindgen::prelude::*;
 
 pub use strum;
@@ -28,17 +33,32 @@ pub mod community;
 pub mod error;
 pub mod instance;
 pub mod language;
+pub mod links;
 pub mod local_user;
+pub mod media;
+pub mod offline;
 pub mod person;
 /// This library is a rip from lemmy's own api_common.
 pub mod post;
+pub mod preferences;
 pub mod sensitive;
 pub mod site;
 
 #[wasm_bindgen]
 extern "C" {
-    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"])]
-    async fn invoke(cmd: &str, args: JsValue) -> JsValue;
+    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "tauri"])]
+    async fn invoke(cmd: &str, args: JsValue) -> core::result::Result<JsValue, JsValue>;
+}
+
+/// Error returned by the backend when a change was saved to be sent once back online
+const QUEUED_OFFLINE: &str = "queued-offline";
+
+async fn invoke_command(cmd: &str, args: JsValue) -> Result<JsValue> {
+    invoke(cmd, args).await.map_err(|e| match e.as_string() {
+        Some(e) if e == QUEUED_OFFLINE => ClientError::QueuedOffline,
+        Some(e) => ClientError::Backend(e),
+        None => ClientError::HttpError,
+    })
 }
 
 #[derive(Clone)]
@@ -62,44 +82,45 @@ struct HttpPostArgs {
     body: String,
 }
 
-async fn get_http(url: &str) -> Option<String> {
+async fn get_http(url: &str) -> Result<String> {
     let args = to_value(&HttpArgs {
         url: url.to_string(),
     })
     .unwrap();
-    let result = invoke("get_http", args).await;
-    result.as_string()
+    let result = invoke_command("get_http", args).await?;
+    result.as_string().ok_or(ClientError::HttpError)
 }
 
 async fn get_json<T>(url: &str) -> Result<T>
 where
     T: DeserializeOwned,
 {
-    let string_data = get_http(url).await.ok_or(ClientError::HttpError)?;
+    let string_data = get_http(url).await?;
     info!("fetching {url}");
     // info!("returned json: {string_data}");
     Ok(serde_json::from_str(&string_data)?)
 }
 
-async fn post_http(url: &str, json_body: &impl Serialize) -> Option<String> {
-    let body = serde_json::to_string(json_body).ok()?;
+/// Sends `json_body` with a backend command that takes a url and a body, like `post_http`
+async fn send_http(command: &str, url: &str, json_body: &impl Serialize) -> Result<String> {
+    let body = serde_json::to_string(json_body)?;
     let args = to_value(&HttpPostArgs {
         url: url.to_string(),
         body,
     })
     .unwrap();
     info!("fetching url {url}");
-    let result = invoke("post_http", args).await;
-    result.as_string()
+    let result = invoke_command(command, args).await?;
+    result.as_string().ok_or(ClientError::HttpError)
 }
 
-async fn post_json<T, D>(url: &str, obj: &D) -> Result<T>
+async fn send_json<T, D>(command: &str, url: &str, obj: &D) -> Result<T>
 where
     T: DeserializeOwned,
     D: Serialize,
 {
     info!("fetching url {url}");
-    let string_data = post_http(url, obj).await.ok_or(ClientError::HttpError)?;
+    let string_data = send_http(command, url, obj).await?;
     info!("{string_data}");
     Ok(serde_json::from_str(&string_data)?)
 }
@@ -107,6 +128,7 @@ where
 pub enum HttpMode {
     GET,
     POST,
+    PUT,
 }
 
 #[async_trait(?Send)]
@@ -129,7 +151,7 @@ pub trait LemmyRequest {
                 let query = serde_qs::to_string(&self)?;
                 Ok(format!("{hostname}/api/v3{path}?{query}"))
             }
-            HttpMode::POST => Ok(format!("{hostname}/api/v3{path}")),
+            HttpMode::POST | HttpMode::PUT => Ok(format!("{hostname}/api/v3{path}")),
         }
     }
 
@@ -149,10 +171,15 @@ pub trait LemmyRequest {
                 return Ok(response);
             }
             HttpMode::POST => {
-                let response = post_json(&url, &self).await?;
+                let response = send_json("post_http", &url, &self).await?;
                 info!("POST received {response:?}");
                 return Ok(response);
             }
+            HttpMode::PUT => {
+                let response = send_json("put_http", &url, &self).await?;
+                info!("PUT received {response:?}");
+                return Ok(response);
+            }
         }
     }
 }
@@ -394,6 +421,91 @@ impl LemmyRequest for CreateComment {
     }
 }
 
+impl LemmyRequest for MarkPostAsRead {
+    type Response = PostResponse;
+
+    fn get_path() -> &'static str {
+        "/post/mark_as_read"
+    }
+
+    fn set_auth(&mut self, jwt: Option<Sensitive<String>>) -> Result<()> {
+        self.auth = jwt.ok_or(ClientError::NotAuthorized)?;
+        Ok(())
+    }
+
+    fn get_http_mode() -> HttpMode {
+        HttpMode::POST
+    }
+}
+
+impl LemmyRequest for BlockPerson {
+    type Response = BlockPersonResponse;
+
+    fn get_path() -> &'static str {
+        "/user/block"
+    }
+
+    fn set_auth(&mut self, jwt: Option<Sensitive<String>>) -> Result<()> {
+        self.auth = jwt.ok_or(ClientError::NotAuthorized)?;
+        Ok(())
+    }
+
+    fn get_http_mode() -> HttpMode {
+        HttpMode::POST
+    }
+}
+
+impl LemmyRequest for BlockCommunity {
+    type Response = BlockCommunityResponse;
+
+    fn get_path() -> &'static str {
+        "/community/block"
+    }
+
+    fn set_auth(&mut self, jwt: Option<Sensitive<String>>) -> Result<()> {
+        self.auth = jwt.ok_or(ClientError::NotAuthorized)?;
+        Ok(())
+    }
+
+    fn get_http_mode() -> HttpMode {
+        HttpMode::POST
+    }
+}
+
+impl LemmyRequest for SaveUserSettings {
+    type Response = LoginResponse;
+
+    fn get_path() -> &'static str {
+        "/user/save_user_settings"
+    }
+
+    fn set_auth(&mut self, jwt: Option<Sensitive<String>>) -> Result<()> {
+        self.auth = jwt.ok_or(ClientError::NotAuthorized)?;
+        Ok(())
+    }
+
+    fn get_http_mode() -> HttpMode {
+        HttpMode::PUT
+    }
+}
+
+impl LemmyRequest for ResolveObject {
+    type Response = ResolveObjectResponse;
+
+    fn get_path() -> &'static str {
+        "/resolve_object"
+    }
+
+    fn set_auth(&mut self, jwt: Option<Sensitive<String>>) -> Result<()> {
+        self.auth = jwt.ok_or(ClientError::NotAuthorized)?;
+        Ok(())
+    }
+
+    fn get_http_mode() -> HttpMode {
+        HttpMode::GET
+    }
+}
+
 impl CapyClient {
     pub async fn execute<T>(&self, args: T) -> Result<T::Response>
     where
@@ -0,0 +1,29 @@
+use serde_wasm_bindgen::from_value;
+use wasm_bindgen::prelude::*;
+
+use crate::{error::Result, invoke_command, CapyClient};
+
+/// Sent by the backend when it has links for the app to open
+const OPEN_LINK_EVENT: &str = "open-link";
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "event"])]
+    fn listen(event: &str, handler: &Closure<dyn FnMut(JsValue)>) -> js_sys::Promise;
+}
+
+impl CapyClient {
+    /// Links the app was launched with or handed since the last call, `lemmy://` or web urls
+    pub async fn take_links(&self) -> Result<Vec<String>> {
+        let links = invoke_command("take_links", JsValue::UNDEFINED).await?;
+        Ok(from_value(links)?)
+    }
+}
+
+/// Calls `f` whenever there are links waiting in `CapyClient::take_links`, for as long as the
+/// page is open
+pub fn on_links(mut f: impl FnMut() + 'static) {
+    let handler = Closure::new(move |_: JsValue| f());
+    let _ = listen(OPEN_LINK_EVENT, &handler);
+    handler.forget();
+}
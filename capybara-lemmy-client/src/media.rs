@@ -0,0 +1,33 @@
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+
+use crate::{error::Result, invoke_command, CapyClient};
+
+#[derive(Serialize)]
+struct ContentTypeArgs<'a> {
+    url: &'a str,
+}
+
+impl CapyClient {
+    /// The `Content-Type` the server reports for `url`, the backend remembers it per url
+    pub async fn content_type(&self, url: &str) -> Result<Option<String>> {
+        let args = to_value(&ContentTypeArgs { url }).unwrap();
+        let content_type = invoke_command("content_type", args).await?;
+        Ok(from_value(content_type)?)
+    }
+}
+
+#[derive(Serialize)]
+struct ProxyImageArgs<'a> {
+    url: &'a str,
+    width: Option<u32>,
+}
+
+impl CapyClient {
+    /// Has the backend download `url` into its image cache, shrunk to `width`, returning the cache key
+    pub async fn proxy_image(&self, url: &str, width: Option<u32>) -> Result<String> {
+        let args = to_value(&ProxyImageArgs { url, width }).unwrap();
+        let key = invoke_command("proxy_image", args).await?;
+        Ok(from_value(key)?)
+    }
+}
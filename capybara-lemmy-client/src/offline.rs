@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use serde_wasm_bindgen::{from_value, to_value};
+
+use crate::{error::Result, invoke_command, sensitive::Sensitive, CapyClient, LemmyRequest};
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OfflineStatus {
+    /// whether the last request failed to reach the server
+    pub offline: bool,
+    /// changes waiting to be sent
+    pub queued: usize,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default)]
+pub struct SyncReport {
+    pub sent: usize,
+    /// changes the server rejected, these are dropped
+    pub failed: usize,
+    pub remaining: usize,
+}
+
+#[derive(Serialize)]
+struct SyncArgs {
+    /// queued changes are stored without the session token, they're sent with the current one
+    auth: Option<Sensitive<String>>,
+}
+
+#[derive(Serialize)]
+struct DownloadArgs {
+    urls: Vec<String>,
+    images: Vec<String>,
+}
+
+impl CapyClient {
+    /// The url `request` is fetched from, used as the key for offline copies
+    pub fn request_url<T>(&self, mut request: T) -> Result<String>
+    where
+        T: LemmyRequest + Serialize,
+    {
+        let auth = self.inner.borrow().jwt.clone();
+        request.set_auth(auth)?;
+        request.get_url(self)
+    }
+
+    /// Saves the responses to `urls` and the `images` so they can be read offline
+    pub async fn download_offline(&self, urls: Vec<String>, images: Vec<String>) -> Result<()> {
+        let args = to_value(&DownloadArgs { urls, images }).unwrap();
+        invoke_command("download_offline", args).await?;
+        Ok(())
+    }
+
+    pub async fn offline_status(&self) -> Result<OfflineStatus> {
+        let status = invoke_command("offline_status", wasm_bindgen::JsValue::UNDEFINED).await?;
+        Ok(from_value(status)?)
+    }
+
+    /// Sends the changes that were made while offline
+    pub async fn sync_offline(&self) -> Result<SyncReport> {
+        let auth = self.inner.borrow().jwt.clone();
+        let args = to_value(&SyncArgs { auth }).unwrap();
+        let report = invoke_command("sync_offline", args).await?;
+        Ok(from_value(report)?)
+    }
+}
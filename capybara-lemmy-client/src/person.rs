@@ -6,7 +6,7 @@ use crate::{
     comment::CommentView,
     community::{CommunityId, CommunityModeratorView},
     instance::InstanceId,
-    post::{PostView, SortType},
+    post::{LanguageId, ListingType, PostView, SortType},
     sensitive::Sensitive,
 };
 
@@ -119,3 +119,66 @@ pub struct PersonAggregates {
     pub comment_count: i64,
     pub comment_score: i64,
 }
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Saves your account settings. Fields left as None aren't changed, and an empty string clears
+/// the avatar, banner, display name, bio or matrix id.
+pub struct SaveUserSettings {
+    /// Show nsfw posts.
+    pub show_nsfw: Option<bool>,
+    /// Show post and comment scores.
+    pub show_scores: Option<bool>,
+    /// Your user's theme.
+    pub theme: Option<String>,
+    pub default_sort_type: Option<SortType>,
+    pub default_listing_type: Option<ListingType>,
+    /// The language of the lemmy interface
+    pub interface_language: Option<String>,
+    /// A URL for your avatar.
+    pub avatar: Option<String>,
+    /// A URL for your banner.
+    pub banner: Option<String>,
+    /// Your display name, which can contain strange characters, and does not need to be unique.
+    pub display_name: Option<String>,
+    /// Your email.
+    pub email: Option<Sensitive<String>>,
+    /// Your bio / info, in markdown.
+    pub bio: Option<String>,
+    /// Your matrix user id. Ex: @my_user:matrix.org
+    pub matrix_user_id: Option<String>,
+    /// Whether to show or hide avatars.
+    pub show_avatars: Option<bool>,
+    /// Sends notifications to your email.
+    pub send_notifications_to_email: Option<bool>,
+    /// Whether this account is a bot account. Users can hide these accounts easily if they wish.
+    pub bot_account: Option<bool>,
+    /// Whether to show bot accounts.
+    pub show_bot_accounts: Option<bool>,
+    /// Whether to show read posts.
+    pub show_read_posts: Option<bool>,
+    /// Whether to show notifications for new posts.
+    pub show_new_post_notifs: Option<bool>,
+    /// A list of languages you are able to see discussion in.
+    pub discussion_languages: Option<Vec<LanguageId>>,
+    /// Generates a TOTP / 2-factor authentication token.
+    pub generate_totp_2fa: Option<bool>,
+    pub auth: Sensitive<String>,
+    /// Open links in a new tab
+    pub open_links_in_new_tab: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Block a person.
+pub struct BlockPerson {
+    pub person_id: PersonId,
+    pub block: bool,
+    pub auth: Sensitive<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// The response for a person block.
+pub struct BlockPersonResponse {
+    pub person_view: PersonView,
+    pub blocked: bool,
+}
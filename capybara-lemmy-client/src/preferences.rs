@@ -0,0 +1,24 @@
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+
+use crate::{error::Result, invoke_command, CapyClient};
+
+#[derive(Serialize)]
+struct SavePreferencesArgs<'a> {
+    preferences: &'a str,
+}
+
+impl CapyClient {
+    /// The preferences json the backend has saved, None before the first save
+    pub async fn load_preferences(&self) -> Result<Option<String>> {
+        let preferences =
+            invoke_command("load_preferences", wasm_bindgen::JsValue::UNDEFINED).await?;
+        Ok(from_value(preferences)?)
+    }
+
+    pub async fn save_preferences(&self, preferences: &str) -> Result<()> {
+        let args = to_value(&SavePreferencesArgs { preferences }).unwrap();
+        invoke_command("save_preferences", args).await?;
+        Ok(())
+    }
+}
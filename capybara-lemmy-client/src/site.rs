@@ -4,12 +4,13 @@ use strum::{Display, EnumString};
 use url::Url;
 
 use crate::{
-    community::Community,
+    comment::CommentView,
+    community::{Community, CommunityView},
     instance::InstanceId,
     language::Language,
     local_user::LocalUserView,
     person::{Person, PersonView},
-    post::{LanguageId, ListingType},
+    post::{LanguageId, ListingType, PostView},
     sensitive::Sensitive,
 };
 
@@ -270,3 +271,21 @@ pub struct CommunityPersonBanView {
     pub community: Community,
     pub person: Person,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// Does an apub fetch for an object.
+pub struct ResolveObject {
+    /// Can be the full url, or a shortened version like: !fediverse@lemmy.ml
+    pub q: String,
+    pub auth: Sensitive<String>,
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+/// The response of an apub object fetch.
+pub struct ResolveObjectResponse {
+    pub comment: Option<CommentView>,
+    pub post: Option<PostView>,
+    pub community: Option<CommunityView>,
+    pub person: Option<PersonView>,
+}
@@ -0,0 +1,43 @@
+#!/bin/sh
+# Puts the stream players videos use into public/vendor, pinned to exact versions. They ship in
+# the bundle so the app never runs scripts from elsewhere, the csp doesn't allow it anyway.
+# A version doesn't pin what the cdn serves, so each file has to match its sha256 as well. When
+# bumping a version, download the file once and put its `sha256sum` output in the hash.
+set -eu
+cd "$(dirname "$0")/.."
+
+HLS_VERSION=1.4.12
+HLS_SHA256=
+DASH_VERSION=4.7.1
+DASH_SHA256=
+
+sha256() {
+    if command -v sha256sum >/dev/null; then
+        sha256sum "$1" | cut -d ' ' -f 1
+    else
+        shasum -a 256 "$1" | cut -d ' ' -f 1
+    fi
+}
+
+mkdir -p public/vendor
+# fetch <url> <file> <sha256>
+fetch() {
+    if [ -z "$3" ]; then
+        echo "no sha256 pinned for $2 in $0" >&2
+        exit 1
+    fi
+    if [ -f "public/vendor/$2" ] && [ "$(sha256 "public/vendor/$2")" = "$3" ]; then
+        return
+    fi
+    rm -f "public/vendor/$2"
+    curl -fsSL "$1" -o "public/vendor/$2.part"
+    actual=$(sha256 "public/vendor/$2.part")
+    if [ "$actual" != "$3" ]; then
+        rm -f "public/vendor/$2.part"
+        echo "$1 has sha256 $actual, expected $3" >&2
+        exit 1
+    fi
+    mv "public/vendor/$2.part" "public/vendor/$2"
+}
+fetch "https://cdn.jsdelivr.net/npm/hls.js@$HLS_VERSION/dist/hls.min.js" "hls-$HLS_VERSION.min.js" "$HLS_SHA256"
+fetch "https://cdn.jsdelivr.net/npm/dashjs@$DASH_VERSION/dist/dash.all.min.js" "dash.all-$DASH_VERSION.min.js" "$DASH_SHA256"
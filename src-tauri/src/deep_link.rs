@@ -0,0 +1,50 @@
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, State};
+
+/// Registered with the OS, `lemmy://lemmy.ml/c/rust` opens the app at that community
+pub const LINK_SCHEME: &str = "lemmy";
+/// Tells the frontend there are links in `take_links`
+const OPEN_LINK_EVENT: &str = "open-link";
+
+/// Links waiting for the frontend. They're queued rather than sent with the event so one that
+/// arrives while the page is still loading isn't lost.
+#[derive(Default)]
+pub struct PendingLinks(Mutex<Vec<String>>);
+
+impl PendingLinks {
+    pub fn push(&self, link: String) {
+        self.0.lock().unwrap().push(link);
+    }
+}
+
+/// The link in a command line, either `lemmy://` or a web url
+pub fn link_from_args(args: &[String]) -> Option<String> {
+    args.iter()
+        .skip(1)
+        .find(|arg| {
+            arg.starts_with(&format!("{LINK_SCHEME}://"))
+                || arg.starts_with("https://")
+                || arg.starts_with("http://")
+        })
+        .cloned()
+}
+
+/// Queues `link` for the frontend and brings the window forward. A second launch without a link
+/// sends an empty one, which only brings the window forward.
+pub fn open(app: &AppHandle, link: String) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    if link.is_empty() {
+        return;
+    }
+    app.state::<PendingLinks>().push(link);
+    let _ = app.emit_all(OPEN_LINK_EVENT, ());
+}
+
+#[tauri::command]
+pub fn take_links(links: State<'_, PendingLinks>) -> Vec<String> {
+    std::mem::take(&mut *links.0.lock().unwrap())
+}
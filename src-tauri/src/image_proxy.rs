@@ -0,0 +1,200 @@
+use std::{
+    fs::{self, File},
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::SystemTime,
+};
+
+use image::{imageops::FilterType, ImageOutputFormat};
+use reqwest::{header, Client};
+use sha2::{Digest, Sha256};
+use tauri::http::{Request, Response, ResponseBuilder};
+
+/// How much space cached images can take up before the least recently used are removed
+const MAX_CACHE_BYTES: u64 = 500 * 1024 * 1024;
+/// Bigger images aren't fetched, they'd mostly be decoded just to be shrunk
+const MAX_IMAGE_BYTES: u64 = 25 * 1024 * 1024;
+/// Gifs are left alone since resizing them would drop the animation
+const RESIZABLE_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+
+/// Images fetched by the backend instead of the webview, so the sites hosting them only ever
+/// see one request and never get a referrer or cookies. Cached on disk, keyed by url and width.
+pub struct ImageProxy {
+    dir: PathBuf,
+    /// the size of everything in the cache, only walked when it's over `MAX_CACHE_BYTES`
+    cached_bytes: AtomicU64,
+    /// held while evicting so two downloads finishing together don't both walk the directory
+    evicting: Mutex<()>,
+}
+
+impl ImageProxy {
+    pub fn open(dir: PathBuf) -> std::io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let cached_bytes = cached_images(&dir).iter().map(|(_, size, _)| size).sum();
+        Ok(Self {
+            dir,
+            cached_bytes: AtomicU64::new(cached_bytes),
+            evicting: Mutex::new(()),
+        })
+    }
+
+    /// A digest rather than `Hash`, whose output can change between rust versions and would
+    /// orphan the whole cache
+    fn key(url: &str, width: Option<u32>) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(url.as_bytes());
+        if let Some(width) = width {
+            hasher.update(format!("@{width}").as_bytes());
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn paths(&self, key: &str) -> (PathBuf, PathBuf) {
+        (self.dir.join(key), self.dir.join(format!("{key}.type")))
+    }
+
+    /// Makes sure `url` is in the cache, shrunk to `width` if it's wider, and returns its key
+    pub async fn fetch(
+        &self,
+        client: &Client,
+        url: &str,
+        width: Option<u32>,
+    ) -> Result<String, String> {
+        let key = Self::key(url, width);
+        let (data_path, type_path) = self.paths(&key);
+        if data_path.exists() {
+            // modification time is what eviction goes by
+            if let Ok(file) = File::options().write(true).open(&data_path) {
+                let _ = file.set_modified(SystemTime::now());
+            }
+            return Ok(key);
+        }
+        // reqwest sends neither a referrer nor cookies, unlike the webview
+        let mut response = client.get(url).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("{url} returned {}", response.status()));
+        }
+        let too_big = || format!("{url} is bigger than {MAX_IMAGE_BYTES} bytes");
+        if response.content_length().unwrap_or_default() > MAX_IMAGE_BYTES {
+            return Err(too_big());
+        }
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|c| c.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        // the length can be missing or wrong, so it's checked while reading too
+        let mut bytes = vec![];
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            if (bytes.len() + chunk.len()) as u64 > MAX_IMAGE_BYTES {
+                return Err(too_big());
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        let (content_type, bytes) = match width {
+            Some(width) if RESIZABLE_TYPES.contains(&content_type.as_str()) => {
+                tauri::async_runtime::spawn_blocking(move || resize(content_type, bytes, width))
+                    .await
+                    .map_err(|e| e.to_string())?
+            }
+            _ => (content_type, bytes),
+        };
+        let size = bytes.len() as u64;
+        fs::write(&data_path, bytes).map_err(|e| e.to_string())?;
+        fs::write(&type_path, content_type).map_err(|e| e.to_string())?;
+        let total = self.cached_bytes.fetch_add(size, Ordering::Relaxed) + size;
+        if total > MAX_CACHE_BYTES {
+            self.evict();
+        }
+        Ok(key)
+    }
+
+    /// Removes the least recently used images until the cache fits in `MAX_CACHE_BYTES`
+    fn evict(&self) {
+        let Ok(_guard) = self.evicting.try_lock() else {
+            return;
+        };
+        let mut images = cached_images(&self.dir);
+        let mut total: u64 = images.iter().map(|(_, size, _)| size).sum();
+        images.sort_by_key(|(modified, _, _)| *modified);
+        for (_, size, path) in images {
+            if total <= MAX_CACHE_BYTES {
+                break;
+            }
+            let _ = fs::remove_file(&path);
+            let _ = fs::remove_file(path.with_extension("type"));
+            total -= size;
+        }
+        self.cached_bytes.store(total, Ordering::Relaxed);
+    }
+
+    /// Serves `image-proxy://localhost/<key>` from the cache, `fetch` puts images there first
+    pub fn protocol(&self, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+        let key = request
+            .uri()
+            .rsplit_once('/')
+            .map(|(_, key)| key)
+            .unwrap_or_default();
+        // keys are hex, anything else could point outside the cache
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_hexdigit()) {
+            return ResponseBuilder::new().status(400).body(vec![]);
+        }
+        let (data_path, type_path) = self.paths(key);
+        match fs::read(data_path) {
+            Ok(bytes) => {
+                let content_type = fs::read_to_string(type_path)
+                    .unwrap_or_else(|_| "application/octet-stream".to_string());
+                ResponseBuilder::new()
+                    .mimetype(&content_type)
+                    .header("Cache-Control", "max-age=31536000, immutable")
+                    .body(bytes)
+            }
+            Err(_) => ResponseBuilder::new().status(404).body(vec![]),
+        }
+    }
+}
+
+/// The last use, size and path of every image in the cache
+fn cached_images(dir: &Path) -> Vec<(SystemTime, u64, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter(|e| e.path().extension().is_none())
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), e.path()))
+        })
+        .collect()
+}
+
+/// Shrinks an image to `width`, re-encoding it as jpeg, or png if it has transparency
+fn resize(content_type: String, bytes: Vec<u8>, width: u32) -> (String, Vec<u8>) {
+    let Ok(image) = image::load_from_memory(&bytes) else {
+        return (content_type, bytes);
+    };
+    if image.width() <= width {
+        return (content_type, bytes);
+    }
+    let resized = image.resize(width, u32::MAX, FilterType::Triangle);
+    let (format, resized_type) = if resized.color().has_alpha() {
+        (ImageOutputFormat::Png, "image/png")
+    } else {
+        (ImageOutputFormat::Jpeg(85), "image/jpeg")
+    };
+    let mut out = Cursor::new(vec![]);
+    match resized.write_to(&mut out, format) {
+        Ok(()) => (resized_type.to_string(), out.into_inner()),
+        Err(_) => (content_type, bytes),
+    }
+}
@@ -1,45 +1,280 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+mod deep_link;
+mod image_proxy;
+mod offline;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use deep_link::{take_links, PendingLinks, LINK_SCHEME};
+use image_proxy::ImageProxy;
+use offline::{is_network_error, OfflineStatus, OfflineStore, SyncReport, QUEUED_OFFLINE};
 use reqwest::Client;
-use tauri::State;
+use tauri::{Manager, State};
 use tauri_plugin_log::LogTarget;
 
+/// Fetches `url`, returning whether the server accepted the request and the body
+async fn fetch(client: &Client, url: &str) -> reqwest::Result<(bool, String)> {
+    let response = client.get(url).send().await?;
+    let success = response.status().is_success();
+    Ok((success, response.text().await?))
+}
+
 #[tauri::command]
-async fn get_http(client: State<'_, Client>, url: String) -> Result<String, String> {
-    Ok(client
-        .get(url)
+async fn get_http(
+    client: State<'_, Client>,
+    offline: State<'_, OfflineStore>,
+    url: String,
+) -> Result<String, String> {
+    match fetch(&client, &url).await {
+        Ok((success, body)) => {
+            offline.set_offline(false);
+            if success {
+                let _ = offline.store_response(&url, &body, false);
+            }
+            Ok(body)
+        }
+        Err(e) if is_network_error(&e) => {
+            offline.set_offline(true);
+            offline
+                .response(&url)
+                .ok_or_else(|| format!("offline and this page wasn't downloaded: {e}"))
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+async fn post_http(
+    client: State<'_, Client>,
+    offline: State<'_, OfflineStore>,
+    url: String,
+    body: String,
+) -> Result<String, String> {
+    let result = client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .body(body.clone())
         .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .text()
-        .await
-        .map_err(|e| e.to_string())?)
+        .await;
+    match result {
+        Ok(response) => {
+            offline.set_offline(false);
+            response.text().await.map_err(|e| e.to_string())
+        }
+        Err(e) if is_network_error(&e) => {
+            offline.set_offline(true);
+            if offline.enqueue(&url, &body) {
+                Err(QUEUED_OFFLINE.to_string())
+            } else {
+                Err(e.to_string())
+            }
+        }
+        Err(e) => Err(e.to_string()),
+    }
 }
 
+/// Like `post_http`, but settings changes aren't worth queueing while offline
 #[tauri::command]
-async fn post_http(client: State<'_, Client>, url: String, body: String) -> Result<String, String> {
-    Ok(client
-        .post(url)
+async fn put_http(
+    client: State<'_, Client>,
+    offline: State<'_, OfflineStore>,
+    url: String,
+    body: String,
+) -> Result<String, String> {
+    let result = client
+        .put(&url)
         .header("Content-Type", "application/json")
         .body(body)
         .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .text()
-        .await
-        .map_err(|e| e.to_string())?)
+        .await;
+    match result {
+        Ok(response) => {
+            offline.set_offline(false);
+            response.text().await.map_err(|e| e.to_string())
+        }
+        Err(e) => {
+            if is_network_error(&e) {
+                offline.set_offline(true);
+            }
+            Err(e.to_string())
+        }
+    }
+}
+
+/// Fetches and pins responses and images so they're still there when offline
+#[tauri::command]
+async fn download_offline(
+    client: State<'_, Client>,
+    offline: State<'_, OfflineStore>,
+    urls: Vec<String>,
+    images: Vec<String>,
+) -> Result<(), String> {
+    for url in urls {
+        let (success, body) = fetch(&client, &url).await.map_err(|e| e.to_string())?;
+        if success {
+            offline
+                .store_response(&url, &body, true)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    for url in images {
+        // a missing image shouldn't stop the rest of the download
+        let Ok(response) = client.get(&url).send().await else {
+            continue;
+        };
+        let content_type = header_content_type(&response);
+        if let Ok(bytes) = response.bytes().await {
+            offline.store_image(&url, content_type.as_deref(), &bytes);
+        }
+    }
+    Ok(())
+}
+
+fn header_content_type(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|c| c.to_str().ok())
+        .map(|c| c.to_string())
+}
+
+/// Asks the server what `url` is without downloading it, for links that don't say in their path
+#[tauri::command]
+async fn content_type(
+    client: State<'_, Client>,
+    offline: State<'_, OfflineStore>,
+    url: String,
+) -> Result<Option<String>, String> {
+    if let Some(content_type) = offline.content_type(&url) {
+        return Ok(content_type);
+    }
+    let response = client.head(&url).send().await.map_err(|e| e.to_string())?;
+    let content_type = if response.status().is_success() {
+        header_content_type(&response)
+    } else {
+        // some servers don't do HEAD, ask for a single byte instead
+        let response = client
+            .get(&url)
+            .header(reqwest::header::RANGE, "bytes=0-0")
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("{url} returned {}", response.status()));
+        }
+        header_content_type(&response)
+    };
+    offline.store_content_type(&url, content_type.as_deref());
+    Ok(content_type)
+}
+
+/// Downloads an image into the proxy cache, resized to `width`, and returns its cache key
+#[tauri::command]
+async fn proxy_image(
+    client: State<'_, Client>,
+    proxy: State<'_, ImageProxy>,
+    url: String,
+    width: Option<u32>,
+) -> Result<String, String> {
+    proxy.fetch(&client, &url, width).await
+}
+
+/// Where the frontend's preferences are kept, as the json it sends
+struct PreferencesFile(PathBuf);
+
+#[tauri::command]
+fn load_preferences(file: State<'_, PreferencesFile>) -> Option<String> {
+    fs::read_to_string(&file.0).ok()
+}
+
+#[tauri::command]
+fn save_preferences(file: State<'_, PreferencesFile>, preferences: String) -> Result<(), String> {
+    // written beside it first so a crash can't leave half a file behind
+    let temp = file.0.with_extension("json.tmp");
+    fs::write(&temp, preferences).map_err(|e| e.to_string())?;
+    fs::rename(&temp, &file.0).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn offline_status(offline: State<'_, OfflineStore>) -> OfflineStatus {
+    offline.status()
+}
+
+#[tauri::command]
+async fn sync_offline(
+    client: State<'_, Client>,
+    offline: State<'_, OfflineStore>,
+    auth: Option<String>,
+) -> Result<SyncReport, String> {
+    Ok(offline.sync(&client, auth.as_deref()).await)
 }
 
 fn main() {
+    // keeps to one instance, a second launch hands its link to the running app through
+    // `register` below and exits
+    tauri_plugin_deep_link::prepare("com.akarras");
     tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::default()
                 .targets([LogTarget::Stdout, LogTarget::Webview])
                 .build(),
         )
-        .manage(Client::new())
-        .invoke_handler(tauri::generate_handler![get_http, post_http])
+        .manage(PendingLinks::default())
+        .manage(
+            Client::builder()
+                .timeout(Duration::from_secs(30))
+                .build()
+                .expect("failed to build http client"),
+        )
+        .setup(|app| {
+            let data_dir = app
+                .path_resolver()
+                .app_data_dir()
+                .expect("no app data directory");
+            fs::create_dir_all(&data_dir)?;
+            app.manage(OfflineStore::open(&data_dir.join("offline.sqlite3"))?);
+            let cache_dir = app
+                .path_resolver()
+                .app_cache_dir()
+                .expect("no app cache directory");
+            app.manage(ImageProxy::open(cache_dir.join("images"))?);
+            let config_dir = app
+                .path_resolver()
+                .app_config_dir()
+                .expect("no app config directory");
+            fs::create_dir_all(&config_dir)?;
+            app.manage(PreferencesFile(config_dir.join("preferences.json")));
+            let args: Vec<String> = std::env::args().collect();
+            if let Some(link) = deep_link::link_from_args(&args) {
+                app.state::<PendingLinks>().push(link);
+            }
+            let handle = app.handle();
+            tauri_plugin_deep_link::register(LINK_SCHEME, move |link| {
+                deep_link::open(&handle, link)
+            })?;
+            Ok(())
+        })
+        .register_uri_scheme_protocol("offline-image", |app, request| {
+            app.state::<OfflineStore>().image_protocol(request)
+        })
+        .register_uri_scheme_protocol("image-proxy", |app, request| {
+            app.state::<ImageProxy>().protocol(request)
+        })
+        .invoke_handler(tauri::generate_handler![
+            get_http,
+            post_http,
+            put_http,
+            download_offline,
+            content_type,
+            proxy_image,
+            load_preferences,
+            save_preferences,
+            take_links,
+            offline_status,
+            sync_offline
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
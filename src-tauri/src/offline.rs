@@ -0,0 +1,347 @@
+use std::{
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::Client;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::http::{Request, Response, ResponseBuilder};
+
+/// Returned as the error of `post_http` when a request was queued instead of sent
+pub const QUEUED_OFFLINE: &str = "queued-offline";
+/// How many responses that weren't explicitly downloaded are kept around
+const MAX_CACHED_RESPONSES: i64 = 1000;
+/// How many links' content types are remembered
+const MAX_CONTENT_TYPES: i64 = 5000;
+/// Endpoints that are safe to send later, in the order they were made
+const QUEUEABLE_PATHS: [&str; 6] = [
+    "/api/v3/post/like",
+    "/api/v3/post/save",
+    "/api/v3/post/mark_as_read",
+    "/api/v3/comment/like",
+    "/api/v3/comment/save",
+    "/api/v3/comment",
+];
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS responses (
+    url TEXT PRIMARY KEY,
+    body TEXT NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    pinned INTEGER NOT NULL DEFAULT 0
+);
+CREATE TABLE IF NOT EXISTS images (
+    url TEXT PRIMARY KEY,
+    content_type TEXT,
+    bytes BLOB NOT NULL
+);
+CREATE TABLE IF NOT EXISTS content_types (
+    url TEXT PRIMARY KEY,
+    content_type TEXT
+);
+CREATE TABLE IF NOT EXISTS queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+";
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// `url` without the session token lemmy takes as the `auth` query parameter, so tokens aren't
+/// written to disk and responses are found again after logging in anew
+fn without_auth(url: &str) -> String {
+    let Ok(mut url) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    let query: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != "auth")
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+    url.to_string()
+}
+
+/// A json request body with its `auth` field set to `auth`, or removed when that's None
+fn with_auth(body: &str, auth: Option<&str>) -> String {
+    let Ok(Value::Object(mut fields)) = serde_json::from_str::<Value>(body) else {
+        return body.to_string();
+    };
+    match auth {
+        Some(auth) => fields.insert("auth".to_string(), Value::String(auth.to_string())),
+        None => fields.remove("auth"),
+    };
+    Value::Object(fields).to_string()
+}
+
+/// Errors that mean we couldn't reach the server at all, as opposed to the server refusing
+pub fn is_network_error(e: &reqwest::Error) -> bool {
+    e.is_connect() || e.is_timeout() || e.is_request()
+}
+
+#[derive(Serialize)]
+pub struct OfflineStatus {
+    offline: bool,
+    queued: usize,
+}
+
+#[derive(Serialize, Default)]
+pub struct SyncReport {
+    sent: usize,
+    /// requests the server rejected, these are dropped from the queue
+    failed: usize,
+    remaining: usize,
+}
+
+/// Removes session tokens saved by versions that kept them in cache keys and queued changes
+fn scrub_tokens(db: &Connection) -> rusqlite::Result<()> {
+    db.execute("DELETE FROM responses WHERE url LIKE '%auth=%'", [])?;
+    let queued = db
+        .prepare("SELECT id, body FROM queue WHERE body LIKE '%\"auth\"%'")?
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (id, body) in queued {
+        db.execute(
+            "UPDATE queue SET body = ?1 WHERE id = ?2",
+            params![with_auth(&body, None), id],
+        )?;
+    }
+    Ok(())
+}
+
+/// Responses, images and unsent changes kept in a local database so the app keeps working
+/// without a connection.
+pub struct OfflineStore {
+    db: Mutex<Connection>,
+    offline: AtomicBool,
+}
+
+impl OfflineStore {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let db = Connection::open(path)?;
+        db.execute_batch(SCHEMA)?;
+        scrub_tokens(&db)?;
+        Ok(Self {
+            db: Mutex::new(db),
+            offline: AtomicBool::new(false),
+        })
+    }
+
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.store(offline, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> OfflineStatus {
+        let queued = self
+            .db
+            .lock()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM queue", [], |row| row.get::<_, i64>(0))
+            .unwrap_or_default();
+        OfflineStatus {
+            offline: self.offline.load(Ordering::Relaxed),
+            queued: queued as usize,
+        }
+    }
+
+    /// Saves a response, pinned responses were downloaded on purpose and never get pruned
+    pub fn store_response(&self, url: &str, body: &str, pinned: bool) -> rusqlite::Result<()> {
+        let url = without_auth(url);
+        let db = self.db.lock().unwrap();
+        db.execute(
+            "INSERT INTO responses (url, body, fetched_at, pinned) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(url) DO UPDATE SET body = ?2, fetched_at = ?3, pinned = pinned OR ?4",
+            params![url, body, now(), pinned],
+        )?;
+        db.execute(
+            "DELETE FROM responses WHERE pinned = 0 AND url NOT IN
+             (SELECT url FROM responses WHERE pinned = 0 ORDER BY fetched_at DESC LIMIT ?1)",
+            params![MAX_CACHED_RESPONSES],
+        )?;
+        Ok(())
+    }
+
+    pub fn response(&self, url: &str) -> Option<String> {
+        self.db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT body FROM responses WHERE url = ?1",
+                params![without_auth(url)],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    pub fn store_image(&self, url: &str, content_type: Option<&str>, bytes: &[u8]) {
+        let _ = self.db.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO images (url, content_type, bytes) VALUES (?1, ?2, ?3)",
+            params![url, content_type, bytes],
+        );
+    }
+
+    fn image(&self, url: &str) -> Option<(Option<String>, Vec<u8>)> {
+        self.db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT content_type, bytes FROM images WHERE url = ?1",
+                params![url],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    /// The content type found for `url` before, `Some(None)` if the server didn't send one
+    pub fn content_type(&self, url: &str) -> Option<Option<String>> {
+        self.db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT content_type FROM content_types WHERE url = ?1",
+                params![url],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    pub fn store_content_type(&self, url: &str, content_type: Option<&str>) {
+        let db = self.db.lock().unwrap();
+        let _ = db.execute(
+            "INSERT OR REPLACE INTO content_types (url, content_type) VALUES (?1, ?2)",
+            params![url, content_type],
+        );
+        // replacing a row gives it a new rowid, so the lowest ones were looked up longest ago
+        let _ = db.execute(
+            "DELETE FROM content_types WHERE rowid NOT IN
+             (SELECT rowid FROM content_types ORDER BY rowid DESC LIMIT ?1)",
+            params![MAX_CONTENT_TYPES],
+        );
+    }
+
+    /// Queues a change made while offline, returns false if it can't be sent later
+    pub fn enqueue(&self, url: &str, body: &str) -> bool {
+        let queueable = reqwest::Url::parse(url)
+            .map(|u| QUEUEABLE_PATHS.contains(&u.path()))
+            .unwrap_or_default();
+        queueable
+            && self
+                .db
+                .lock()
+                .unwrap()
+                .execute(
+                    "INSERT INTO queue (url, body, created_at) VALUES (?1, ?2, ?3)",
+                    params![url, with_auth(body, None), now()],
+                )
+                .is_ok()
+    }
+
+    fn next_queued(&self) -> Option<(i64, String, String)> {
+        self.db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT id, url, body FROM queue ORDER BY id LIMIT 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .ok()
+            .flatten()
+    }
+
+    fn dequeue(&self, id: i64) {
+        let _ = self
+            .db
+            .lock()
+            .unwrap()
+            .execute("DELETE FROM queue WHERE id = ?1", params![id]);
+    }
+
+    /// Sends queued changes in order as the account logged in with `auth`, stopping at the first
+    /// one that can't reach the server. Nothing is sent while logged out.
+    pub async fn sync(&self, client: &Client, auth: Option<&str>) -> SyncReport {
+        let mut report = SyncReport::default();
+        if auth.is_none() {
+            report.remaining = self.status().queued;
+            return report;
+        }
+        while let Some((id, url, body)) = self.next_queued() {
+            let result = client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(with_auth(&body, auth))
+                .send()
+                .await;
+            match result {
+                Ok(response) => {
+                    self.set_offline(false);
+                    if response.status().is_success() {
+                        report.sent += 1;
+                    } else {
+                        report.failed += 1;
+                    }
+                    self.dequeue(id);
+                }
+                Err(e) if is_network_error(&e) => {
+                    self.set_offline(true);
+                    break;
+                }
+                Err(_) => {
+                    report.failed += 1;
+                    self.dequeue(id);
+                }
+            }
+        }
+        report.remaining = self.status().queued;
+        report
+    }
+
+    /// Serves `offline-image://localhost/<url encoded image url>` from downloaded images
+    pub fn image_protocol(
+        &self,
+        request: &Request,
+    ) -> Result<Response, Box<dyn std::error::Error>> {
+        let path = request
+            .uri()
+            .split_once("localhost/")
+            .map(|(_, path)| path)
+            .unwrap_or_default();
+        let url = percent_encoding::percent_decode_str(path).decode_utf8()?;
+        match self.image(&url) {
+            Some((content_type, bytes)) => ResponseBuilder::new()
+                .mimetype(
+                    content_type
+                        .as_deref()
+                        .unwrap_or("application/octet-stream"),
+                )
+                .body(bytes),
+            None => ResponseBuilder::new().status(404).body(vec![]),
+        }
+    }
+}
@@ -0,0 +1,264 @@
+use capybara_lemmy_client::{
+    local_user::{LocalUser, LocalUserView},
+    person::{Person, SaveUserSettings},
+    post::{ListingType, SortType},
+    site::{GetSite, LocalSite},
+    CapyClient,
+};
+use leptos::*;
+
+use crate::{
+    app::CurrentUser,
+    blocks::Blocks,
+    components::{
+        sorting_components::{SortMenu, TypeMenu},
+        toast::Toasts,
+    },
+};
+
+/// The logged in account's profile and settings, as its server has them
+#[derive(Clone, Copy)]
+pub struct Account {
+    pub user: RwSignal<Option<LocalUserView>>,
+    /// the server's own settings, which guests get
+    pub site: RwSignal<Option<LocalSite>>,
+    /// whether the server has answered, until then the defaults above aren't known
+    pub loaded: RwSignal<bool>,
+}
+
+impl Account {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            user: create_rw_signal(cx, None),
+            site: create_rw_signal(cx, None),
+            loaded: create_rw_signal(cx, false),
+        }
+    }
+
+    /// Fetches the account the client is logged in as, and its blocks along with it
+    pub fn load(&self, cx: Scope) {
+        self.user.set(None);
+        self.loaded.set(false);
+        self.fetch(cx);
+    }
+
+    fn fetch(&self, cx: Scope) {
+        let account = *self;
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            if let Ok(site) = client.execute(GetSite::default()).await {
+                account.site.set(Some(site.site_view.local_site));
+                if let Some(me) = &site.my_user {
+                    use_context::<Blocks>(cx).unwrap().set(me);
+                }
+                account.user.set(site.my_user.map(|me| me.local_user_view));
+            }
+            // offline or not, feeds go ahead with what they have
+            account.loaded.set(true);
+        });
+    }
+
+    /// A setting of the account, None while logged out or loading
+    pub fn setting<T>(&self, f: impl FnOnce(&LocalUser) -> T) -> Option<T> {
+        self.user.with(|u| u.as_ref().map(|u| f(&u.local_user)))
+    }
+
+    /// The account's sort, None leaves it to the server
+    pub fn default_sort(&self) -> Option<SortType> {
+        self.setting(|u| u.default_sort_type)
+    }
+
+    /// The account's listing type, or the site's for guests
+    pub fn default_listing_type(&self) -> Option<ListingType> {
+        self.setting(|u| u.default_listing_type).or_else(|| {
+            self.site
+                .with(|s| s.as_ref().map(|s| s.default_post_listing_type))
+        })
+    }
+
+    pub fn show_scores(&self) -> bool {
+        self.setting(|u| u.show_scores).unwrap_or(true)
+    }
+
+    pub fn show_avatars(&self) -> bool {
+        self.setting(|u| u.show_avatars).unwrap_or(true)
+    }
+
+    pub fn show_read_posts(&self) -> bool {
+        self.setting(|u| u.show_read_posts).unwrap_or(true)
+    }
+
+    /// Bots are hidden when the account says not to show them
+    pub fn hides_creator(&self, creator: &Person) -> bool {
+        creator.bot_account && self.setting(|u| !u.show_bot_accounts).unwrap_or_default()
+    }
+
+    /// Sends `settings` to the server and fetches the account again once it has them
+    pub fn save(&self, cx: Scope, settings: SaveUserSettings) {
+        let account = *self;
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            let toasts = use_context::<Toasts>(cx).unwrap();
+            match client.execute(settings).await {
+                Ok(_) => {
+                    toasts.info("saved account settings");
+                    account.fetch(cx);
+                }
+                Err(e) => toasts.error(e),
+            }
+        });
+    }
+}
+
+/// What the form starts from, empty strings clear a field on the server
+fn current_settings(user: &LocalUserView) -> SaveUserSettings {
+    let (local, person) = (&user.local_user, &user.person);
+    let url =
+        |url: &Option<url::Url>| Some(url.as_ref().map(|u| u.to_string()).unwrap_or_default());
+    SaveUserSettings {
+        show_nsfw: Some(local.show_nsfw),
+        show_scores: Some(local.show_scores),
+        default_sort_type: Some(local.default_sort_type),
+        default_listing_type: Some(local.default_listing_type),
+        avatar: url(&person.avatar),
+        banner: url(&person.banner),
+        display_name: Some(person.display_name.clone().unwrap_or_default()),
+        bio: Some(person.bio.clone().unwrap_or_default()),
+        matrix_user_id: Some(person.matrix_user_id.clone().unwrap_or_default()),
+        show_avatars: Some(local.show_avatars),
+        send_notifications_to_email: Some(local.send_notifications_to_email),
+        bot_account: Some(person.bot_account),
+        show_bot_accounts: Some(local.show_bot_accounts),
+        show_read_posts: Some(local.show_read_posts),
+        show_new_post_notifs: Some(local.show_new_post_notifs),
+        ..Default::default()
+    }
+}
+
+#[component]
+fn TextSetting(
+    cx: Scope,
+    draft: RwSignal<SaveUserSettings>,
+    label: &'static str,
+    get: fn(&SaveUserSettings) -> Option<String>,
+    set: fn(&mut SaveUserSettings, String),
+) -> impl IntoView {
+    view! { cx,
+        <label class="flex flex-row gap-2">
+            <div class="w-32">{label}</div>
+            <input
+                class="bg-neutral-800 rounded px-1 grow"
+                prop:value=move || draft.with(|d| get(d).unwrap_or_default())
+                on:input=move |e| draft.update(|d| set(d, event_target_value(&e)))
+            />
+        </label>
+    }
+}
+
+#[component]
+fn ToggleSetting(
+    cx: Scope,
+    draft: RwSignal<SaveUserSettings>,
+    label: &'static str,
+    get: fn(&SaveUserSettings) -> Option<bool>,
+    set: fn(&mut SaveUserSettings, bool),
+) -> impl IntoView {
+    view! { cx,
+        <label class="flex flex-row gap-2">
+            <input
+                type="checkbox"
+                prop:checked=move || draft.with(|d| get(d).unwrap_or_default())
+                on:change=move |e| draft.update(|d| set(d, event_target_checked(&e)))
+            />
+            {label}
+        </label>
+    }
+}
+
+/// The profile and preferences kept by the account's server
+#[component]
+pub fn AccountSettingsView(cx: Scope) -> impl IntoView {
+    let account = use_context::<Account>(cx).unwrap();
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    let loaded = create_memo(cx, move |_| account.user.with(|u| u.is_some()));
+    let draft = create_rw_signal(cx, SaveUserSettings::default());
+    let (sort, set_sort) = create_signal(cx, None);
+    let (type_, set_type) = create_signal(cx, None);
+    let reset = move |user: &LocalUserView| {
+        let settings = current_settings(user);
+        set_sort(settings.default_sort_type);
+        set_type(settings.default_listing_type);
+        draft.set(settings);
+    };
+    // start over from the server's copy whenever it's fetched
+    create_effect(cx, move |_| {
+        account.user.with(|u| {
+            if let Some(user) = u {
+                reset(user);
+            }
+        })
+    });
+    create_effect(cx, move |_| {
+        let (sort, type_) = (sort(), type_());
+        draft.update(|d| {
+            d.default_sort_type = sort;
+            d.default_listing_type = type_;
+        });
+    });
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"Account"</div>
+            {move || match (current_user.with(|u| u.is_some()), loaded()) {
+                (false, _) => view! { cx, <div>"Log in to change your account's settings"</div> }.into_view(cx),
+                (true, false) => view! { cx, <div>"Loading"</div> }.into_view(cx),
+                (true, true) => view! { cx,
+                    <div class="text-xl">"Profile"</div>
+                    <TextSetting draft label="display name" get=|d| d.display_name.clone() set=|d, v| d.display_name = Some(v)/>
+                    <TextSetting draft label="avatar url" get=|d| d.avatar.clone() set=|d, v| d.avatar = Some(v)/>
+                    <TextSetting draft label="banner url" get=|d| d.banner.clone() set=|d, v| d.banner = Some(v)/>
+                    <TextSetting draft label="matrix id" get=|d| d.matrix_user_id.clone() set=|d, v| d.matrix_user_id = Some(v)/>
+                    <label class="flex flex-col gap-1">
+                        "bio"
+                        <textarea
+                            class="bg-neutral-800 rounded p-1 h-32"
+                            prop:value=move || draft.with(|d| d.bio.clone().unwrap_or_default())
+                            on:input=move |e| draft.update(|d| d.bio = Some(event_target_value(&e)))
+                        />
+                    </label>
+                    <ToggleSetting draft label="this is a bot account" get=|d| d.bot_account set=|d, v| d.bot_account = Some(v)/>
+                    <div class="text-xl">"Preferences"</div>
+                    <div class="flex flex-row gap-2">
+                        "default sort:"
+                        <SortMenu sort set_sort/>
+                        <TypeMenu type_ set_type/>
+                    </div>
+                    <ToggleSetting draft label="show nsfw" get=|d| d.show_nsfw set=|d, v| d.show_nsfw = Some(v)/>
+                    <ToggleSetting draft label="show scores" get=|d| d.show_scores set=|d, v| d.show_scores = Some(v)/>
+                    <ToggleSetting draft label="show avatars" get=|d| d.show_avatars set=|d, v| d.show_avatars = Some(v)/>
+                    <ToggleSetting draft label="show bot accounts" get=|d| d.show_bot_accounts set=|d, v| d.show_bot_accounts = Some(v)/>
+                    <ToggleSetting draft label="show read posts" get=|d| d.show_read_posts set=|d, v| d.show_read_posts = Some(v)/>
+                    <ToggleSetting draft label="notify about new posts" get=|d| d.show_new_post_notifs set=|d, v| d.show_new_post_notifs = Some(v)/>
+                    <ToggleSetting draft label="send notifications to email" get=|d| d.send_notifications_to_email set=|d, v| d.send_notifications_to_email = Some(v)/>
+                    <div class="flex flex-row gap-2">
+                        <button
+                            class="bg-neutral-700 hover:bg-neutral-500 px-2 rounded"
+                            on:click=move |_| account.save(cx, draft.get_untracked())
+                        >
+                            "save"
+                        </button>
+                        <button
+                            class="bg-neutral-800 hover:bg-neutral-600 px-2 rounded"
+                            on:click=move |_| account.user.with_untracked(|u| {
+                                if let Some(user) = u {
+                                    reset(user);
+                                }
+                            })
+                        >
+                            "reset"
+                        </button>
+                    </div>
+                }.into_view(cx),
+            }}
+        </div>
+    }
+}
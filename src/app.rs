@@ -1,8 +1,29 @@
 use crate::{
+    account::{Account, AccountSettingsView},
+    blocks::{BlockList, Blocks},
     community::Community,
     community_list::CommunityList,
-    components::{feed::post_preview::*, post::Post, posts::Posts, profile::Profile},
+    components::{
+        feed::{
+            embeds::EmbedSettingsView,
+            lightbox::{Lightbox, LightboxView},
+            media::MediaTypes,
+            post_preview::*,
+        },
+        offline::{Offline, OfflineIndicator},
+        person::PersonPage,
+        post::Post,
+        posts::Posts,
+        profile::Profile,
+        toast::{ToastList, Toasts},
+    },
+    drafts::DraftList,
+    filters::{ContentFilters, FilterList},
     login::Login,
+    mutation::ItemStore,
+    nsfw::NsfwSettingsView,
+    preferences::{provide_preference_contexts, PreferenceStore, PreferencesView},
+    routing::LinkHandler,
     settings::{LoginInfo, Settings},
 };
 use capybara_lemmy_client::CapyClient;
@@ -48,9 +69,27 @@ pub fn App(cx: Scope) -> impl IntoView {
     let jwt = Settings::current_login();
     let current_user = CurrentUser(create_rw_signal(cx, jwt.clone()));
     info!("{jwt:?}");
-    provide_context(cx, GlobalViewMode(create_rw_signal(cx, ViewMode::Default)));
-    provide_context(cx, GlobalBlurState(create_rw_signal(cx, true)));
+    let preferences = PreferenceStore::new(cx);
+    provide_context(cx, preferences);
+    provide_preference_contexts(cx, preferences);
+    preferences.apply_appearance(cx);
+    provide_context(
+        cx,
+        GlobalViewMode(create_rw_signal(cx, preferences.with(|p| p.view_mode))),
+    );
     provide_context(cx, current_user);
+    let item_store = ItemStore::new(cx);
+    provide_context(cx, item_store);
+    provide_context(cx, Toasts::new(cx));
+    provide_context(cx, Offline::new(cx));
+    let media_types = MediaTypes::new(cx);
+    provide_context(cx, media_types);
+    provide_context(cx, Lightbox::new(cx, media_types));
+    provide_context(cx, ContentFilters::new(cx));
+    let blocks = Blocks::new(cx);
+    provide_context(cx, blocks);
+    let account = Account::new(cx);
+    provide_context(cx, account);
     provide_context(
         cx,
         CapyClient::new(
@@ -58,12 +97,22 @@ pub fn App(cx: Scope) -> impl IntoView {
                 .0
                 .get_untracked()
                 .map(|u| u.instance.to_string())
-                .unwrap_or("https://lemmy.world".to_string()),
+                .unwrap_or_else(|| preferences.with(|p| p.default_instance.clone())),
             current_user.0.get_untracked().map(|user| user.jwt.clone()),
         ),
     );
-    let hide_read = HideRead(create_rw_signal(cx, false));
-    provide_context(cx, hide_read);
+    preferences.sync(cx);
+    // logged out, the client goes to the default instance
+    let default_instance = create_memo(cx, move |_| {
+        preferences.with(|p| p.default_instance.clone())
+    });
+    create_effect(cx, move |_| {
+        if current_user.with(|u| u.is_none()) {
+            use_context::<CapyClient>(cx)
+                .unwrap()
+                .set_instance(default_instance());
+        }
+    });
     create_effect(cx, move |_| {
         let user = current_user();
         Settings::set_current_login(user.clone());
@@ -74,17 +123,28 @@ pub fn App(cx: Scope) -> impl IntoView {
         client.set_jwt(user.map(|u| u.jwt));
         // clear session storage when switching users to prevent issues with cache tricks
         SessionStorage::clear();
+        item_store.clear();
+        blocks.clear();
+        account.load(cx);
     });
     // keeps a unique key to refresh the user list
     let user_list = create_rw_signal(cx, 0);
     view! { cx,
         <Body class="bg-neutral-100 dark:bg-neutral-900 text-base dark:text-white"/>
+        <ToastList/>
+        <LightboxView/>
         <main class="container mx-auto px-4">
             <nav class="flex flex-row gap-2 sticky top-0 bg-neutral-900 z-50">
                 <a href="/">"home"</a>
                 <a href="/login">"Login"</a>
                 <a href="/communities">"Communities"</a>
+                <a href="/drafts">"Drafts"</a>
+                <a href="/filters">"Filters"</a>
+                <a href="/blocks">"Blocks"</a>
+                <a href="/settings">"Settings"</a>
+                <a href="/account">"Account"</a>
                 <Profile/>
+                <OfflineIndicator/>
                 {move || {
                     let mut logins = Settings::get_logins();
                     let user = current_user();
@@ -127,6 +187,7 @@ pub fn App(cx: Scope) -> impl IntoView {
                 }}
             </nav>
             <Router>
+                <LinkHandler/>
                 <Routes>
                     <Route
                         path="/login"
@@ -146,6 +207,54 @@ pub fn App(cx: Scope) -> impl IntoView {
                             view! { cx, <CommunityList/> }
                         }
                     />
+                    <Route
+                        path="/drafts"
+                        view=move |cx| {
+                            view! { cx, <DraftList/> }
+                        }
+                    />
+                    <Route
+                        path="/filters"
+                        view=move |cx| {
+                            view! { cx, <FilterList/> }
+                        }
+                    />
+                    <Route
+                        path="/blocks"
+                        view=move |cx| {
+                            view! { cx, <BlockList/> }
+                        }
+                    />
+                    <Route
+                        path="/embeds"
+                        view=move |cx| {
+                            view! { cx, <EmbedSettingsView/> }
+                        }
+                    />
+                    <Route
+                        path="/account"
+                        view=move |cx| {
+                            view! { cx, <AccountSettingsView/> }
+                        }
+                    />
+                    <Route
+                        path="/settings"
+                        view=move |cx| {
+                            view! { cx, <PreferencesView/> }
+                        }
+                    />
+                    <Route
+                        path="/nsfw"
+                        view=move |cx| {
+                            view! { cx, <NsfwSettingsView/> }
+                        }
+                    />
+                    <Route
+                        path="/person/:person"
+                        view=move |cx| {
+                            view! { cx, <PersonPage/> }
+                        }
+                    />
                     <Route
                         path="/c/:community"
                         view=move |cx| {
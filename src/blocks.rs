@@ -0,0 +1,210 @@
+use capybara_lemmy_client::{
+    comment::CommentView,
+    community::{BlockCommunity, Community, CommunityId},
+    person::{BlockPerson, Person, PersonId},
+    post::PostView,
+    site::MyUserInfo,
+    CapyClient,
+};
+use leptos::*;
+
+use crate::{app::CurrentUser, components::toast::Toasts};
+
+/// People and communities the current account has blocked on its server
+#[derive(Clone, Copy)]
+pub struct Blocks {
+    pub people: RwSignal<Vec<Person>>,
+    pub communities: RwSignal<Vec<Community>>,
+    /// until the lists are fetched we go by what the server marked on each item
+    loaded: RwSignal<bool>,
+}
+
+impl Blocks {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            people: create_rw_signal(cx, vec![]),
+            communities: create_rw_signal(cx, vec![]),
+            loaded: create_rw_signal(cx, false),
+        }
+    }
+
+    /// Forgets the blocks of the previous account, until `set` is given the new one's
+    pub fn clear(&self) {
+        self.loaded.set(false);
+        self.people.set(vec![]);
+        self.communities.set(vec![]);
+    }
+
+    /// Takes the blocks from the account's `GetSite`, which `Account` fetches anyway
+    pub fn set(&self, me: &MyUserInfo) {
+        self.people
+            .set(me.person_blocks.iter().map(|b| b.target.clone()).collect());
+        self.communities.set(
+            me.community_blocks
+                .iter()
+                .map(|b| b.community.clone())
+                .collect(),
+        );
+        self.loaded.set(true);
+    }
+
+    pub fn is_person_blocked(&self, person_id: PersonId) -> bool {
+        self.people.with(|p| p.iter().any(|p| p.id == person_id))
+    }
+
+    pub fn is_community_blocked(&self, community_id: CommunityId) -> bool {
+        self.communities
+            .with(|c| c.iter().any(|c| c.id == community_id))
+    }
+
+    /// Whether the creator of something is blocked, `creator_blocked` is what the server said
+    /// when it was fetched
+    pub fn creator_blocked(&self, creator_id: PersonId, creator_blocked: bool) -> bool {
+        if self.loaded.get() {
+            self.is_person_blocked(creator_id)
+        } else {
+            creator_blocked
+        }
+    }
+
+    pub fn hides_post(&self, post: &PostView) -> bool {
+        self.creator_blocked(post.creator.id, post.creator_blocked)
+            || self.is_community_blocked(post.community.id)
+    }
+
+    /// Comments from blocked people are hidden along with their replies
+    pub fn hides_comment(&self, comment: &CommentView) -> bool {
+        self.creator_blocked(comment.creator.id, comment.creator_blocked)
+    }
+
+    pub fn block_person(&self, cx: Scope, person: Person, block: bool) {
+        let blocks = *self;
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            let toasts = use_context::<Toasts>(cx).unwrap();
+            let request = BlockPerson {
+                person_id: person.id,
+                block,
+                ..Default::default()
+            };
+            match client.execute(request).await {
+                Ok(response) => {
+                    blocks.people.update(|p| {
+                        p.retain(|p| p.id != person.id);
+                        if response.blocked {
+                            p.push(person.clone());
+                        }
+                    });
+                    let action = if response.blocked {
+                        "blocked"
+                    } else {
+                        "unblocked"
+                    };
+                    toasts.info(format!("{action} {}", person.name));
+                }
+                Err(e) => toasts.error(e),
+            }
+        });
+    }
+
+    pub fn block_community(&self, cx: Scope, community: Community, block: bool) {
+        let blocks = *self;
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            let toasts = use_context::<Toasts>(cx).unwrap();
+            let request = BlockCommunity {
+                community_id: community.id,
+                block,
+                ..Default::default()
+            };
+            match client.execute(request).await {
+                Ok(response) => {
+                    blocks.communities.update(|c| {
+                        c.retain(|c| c.id != community.id);
+                        if response.blocked {
+                            c.push(community.clone());
+                        }
+                    });
+                    let action = if response.blocked {
+                        "blocked"
+                    } else {
+                        "unblocked"
+                    };
+                    toasts.info(format!("{action} {}", community.name));
+                }
+                Err(e) => toasts.error(e),
+            }
+        });
+    }
+}
+
+/// `name@instance` for showing people and communities from other instances
+fn full_name(name: &str, actor_id: &url::Url) -> String {
+    format!("{name}@{}", actor_id.host_str().unwrap_or_default())
+}
+
+#[component]
+pub fn BlockList(cx: Scope) -> impl IntoView {
+    let blocks = use_context::<Blocks>(cx).unwrap();
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"Blocks"</div>
+            {move || current_user.with(|u| u.is_none()).then(|| view! { cx,
+                <div class="text-yellow-500">"Log in to block people and communities"</div>
+            })}
+            <div class="text-xl">"People"</div>
+            {move || blocks.people.with(|p| p.is_empty()).then(|| view! { cx, "No blocked people" })}
+            {move || {
+                blocks
+                    .people
+                    .get()
+                    .into_iter()
+                    .map(|person| {
+                        let name = full_name(&person.name, &person.actor_id);
+                        view! { cx,
+                            <div class="flex flex-row gap-2 bg-neutral-800 p-2 rounded">
+                                <div class="grow">{name}</div>
+                                <button
+                                    class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                                    on:click=move |_| blocks.block_person(cx, person.clone(), false)
+                                >
+                                    "unblock"
+                                </button>
+                            </div>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+            <div class="text-xl">"Communities"</div>
+            {move || blocks.communities.with(|c| c.is_empty()).then(|| view! { cx, "No blocked communities" })}
+            {move || {
+                blocks
+                    .communities
+                    .get()
+                    .into_iter()
+                    .map(|community| {
+                        let name = full_name(&community.name, &community.actor_id);
+                        view! { cx,
+                            <div class="flex flex-row gap-2 bg-neutral-800 p-2 rounded">
+                                <div class="grow">{name}</div>
+                                <button
+                                    class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                                    on:click=move |_| blocks.block_community(cx, community.clone(), false)
+                                >
+                                    "unblock"
+                                </button>
+                            </div>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+            <div class="text-xl">"Instances"</div>
+            <div class="text-gray-500">
+                "Lemmy doesn't support blocking whole instances yet. "
+                <a class="underline text-red-400 hover:text-red-600" href="/filters">"Add an instance filter"</a>
+                " to hide everything from one instead."
+            </div>
+        </div>
+    }
+}
@@ -28,6 +28,14 @@ impl CommunityKey {
             CommunityKey::Name(url) => Some(url.to_string()),
         }
     }
+
+    /// Identifies the community in settings stored per community
+    pub fn storage_key(&self) -> String {
+        match self {
+            CommunityKey::Id(id) => format!("id:{}", id.0),
+            CommunityKey::Name(name) => format!("name:{name}"),
+        }
+    }
 }
 
 #[component]
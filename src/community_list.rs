@@ -1,6 +1,6 @@
 use capybara_lemmy_client::{
     community::{CommunityAggregates, CommunityView, ListCommunities},
-    post::SortType,
+    post::{ListingType, SortType},
     CapyClient,
 };
 use leptos::*;
@@ -16,6 +16,8 @@ use crate::{
         subscribe::SubscribeButton,
         time::RelativeTime,
     },
+    nsfw::Nsfw,
+    routing::{query_param, query_writer, set_query_param},
 };
 
 #[component]
@@ -36,8 +38,13 @@ pub fn CommunityView(cx: Scope, community: CommunityView) -> impl IntoView {
         ..
     } = counts;
     let subscribed = create_rw_signal(cx, subscribed);
+    let nsfw = use_context::<Nsfw>(cx).unwrap();
+    let hidden = {
+        let community = community.clone();
+        move || nsfw.hides_community(&community)
+    };
     view! { cx,
-        <div class="flex flex-col p-5 border-2 border-neutral-700 bg-neutral-800 text-neutral-100 gap-2">
+        <div class="flex flex-col p-5 border-2 border-neutral-700 bg-neutral-800 text-neutral-100 gap-2" class:hidden=hidden>
             <div class="flex flex-row gap-1 text-2xl">
                 <CommunityBadge community subscribed />
                 "created: "<RelativeTime time=published />
@@ -55,9 +62,14 @@ pub fn CommunityView(cx: Scope, community: CommunityView) -> impl IntoView {
 
 #[component]
 pub fn CommunityList(cx: Scope) -> impl IntoView {
-    let (show_nsfw, set_show_nsfw) = create_signal(cx, None);
-    let (sort, set_sort) = create_signal(cx, Some(SortType::TopAll));
-    let (type_, set_type) = create_signal(cx, None);
+    // everything is in the query string, `?sort=Hot&type=Local&nsfw=false`
+    let show_nsfw = query_param::<bool>(cx, "nsfw");
+    let query_sort = query_param::<SortType>(cx, "sort");
+    let sort = create_memo(cx, move |_| query_sort().or(Some(SortType::TopAll)));
+    let type_ = query_param::<ListingType>(cx, "type");
+    // the menus only write, what's shown comes from the url
+    let set_sort = query_writer::<SortType>(cx, "sort");
+    let set_type = query_writer::<ListingType>(cx, "type");
     let current_user = use_context::<CurrentUser>(cx).unwrap();
     let communities = create_local_resource(
         cx,
@@ -82,11 +94,12 @@ pub fn CommunityList(cx: Scope) -> impl IntoView {
                 <SortMenu sort set_sort />
                 <TypeMenu type_ set_type />
                 <button class="p-1 bg-neutral-800 hover:bg-neutral-600" on:click=move |_| {
-                    set_show_nsfw(match show_nsfw.get_untracked() {
+                    let next = match show_nsfw.get_untracked() {
                         Some(true) => Some(false),
                         Some(false) => None,
                         None => Some(true)
-                    })
+                    };
+                    set_query_param(cx, "nsfw", next.map(|n| n.to_string()));
                 }>{move || match show_nsfw() {
                     Some(true) => "nsfw only",
                     Some(false) => "no nsfw",
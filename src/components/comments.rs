@@ -1,33 +1,66 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use capybara_lemmy_client::{
     comment::{
-        Comment, CommentAggregates, CommentSortType, CommentView, CreateComment, CreateCommentLike,
-        GetComments, SaveComment,
+        Comment, CommentAggregates, CommentId, CommentSortType, CommentView, CreateComment,
+        CreateCommentLike, GetComments, SaveComment,
     },
-    post::PostId,
+    post::{PostId, PostView},
     CapyClient,
 };
+use gloo::utils::document;
 use leptos::*;
 use leptos_icons::{BiIcon, BsIcon, Icon};
-use log::info;
 use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsCast;
 
 use crate::{
-    app::CurrentUser,
+    account::Account,
+    blocks::Blocks,
     components::{
         feed::virtual_scroll::InfinitePage,
         markdown::Markdown,
         person::PersonView,
-        reply_box::{ReplyBox, ReplyButton},
+        reply_box::{PendingReplies, PendingReplyView, ReplyBox, ReplyButton},
         save_button::SaveButton,
+        share::{app_link, instance_link, ShareMenu},
         sorting_components::CommentSortMenu,
         time::RelativeTime,
         voter::Voter,
     },
+    filters::ContentFilters,
+    mutation::{CommentState, ItemStore},
+    settings::{CollapseRules, Settings},
 };
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Comments are fetched this many at a time, later pages too so they line up with the first
+pub const COMMENT_PAGE_SIZE: i64 = 50;
+
+/// Colours for the collapse bar, so it's easier to follow which thread a comment belongs to
+const DEPTH_COLORS: [&str; 6] = [
+    "bg-red-300 hover:bg-red-600",
+    "bg-orange-300 hover:bg-orange-600",
+    "bg-yellow-300 hover:bg-yellow-600",
+    "bg-green-300 hover:bg-green-600",
+    "bg-blue-300 hover:bg-blue-600",
+    "bg-purple-300 hover:bg-purple-600",
+];
+
+/// Collapse state shared by every comment on a post
+#[derive(Clone, Copy)]
+struct CollapseState {
+    /// comments the user explicitly collapsed or expanded, these win over the rules
+    overrides: RwSignal<HashMap<i32, bool>>,
+    rules: RwSignal<CollapseRules>,
+    collapse_top_level: RwSignal<bool>,
+}
+
+/// Comments with their reply box open. Comments are only rendered near the viewport, so this
+/// lives with the post and a comment scrolled back into view opens its box again.
+#[derive(Clone, Copy)]
+struct OpenReplies(StoredValue<HashSet<CommentId>>);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
 pub struct CommentWithChildren(pub CommentView, pub Vec<CommentWithChildren>);
 
 fn get_children(
@@ -50,6 +83,43 @@ fn get_children(
 }
 
 impl CommentWithChildren {
+    pub fn find(thread: &[Self], id: CommentId) -> Option<&Self> {
+        thread.iter().find_map(|c| {
+            if c.0.comment.id == id {
+                Some(c)
+            } else {
+                Self::find(&c.1, id)
+            }
+        })
+    }
+
+    pub fn find_mut(thread: &mut [Self], id: CommentId) -> Option<&mut Self> {
+        thread.iter_mut().find_map(|c| {
+            if c.0.comment.id == id {
+                Some(c)
+            } else {
+                Self::find_mut(&mut c.1, id)
+            }
+        })
+    }
+
+    /// The replies to `parent`, or the top level comments for None
+    pub fn children_mut(
+        thread: &mut Vec<Self>,
+        parent: Option<CommentId>,
+    ) -> Option<&mut Vec<Self>> {
+        match parent {
+            Some(parent) => Self::find_mut(thread, parent).map(|c| &mut c.1),
+            None => Some(thread),
+        }
+    }
+
+    /// Takes a comment and its replies out of the thread
+    pub fn remove(thread: &mut Vec<Self>, id: CommentId) {
+        thread.retain(|c| c.0.comment.id != id);
+        thread.iter_mut().for_each(|c| Self::remove(&mut c.1, id));
+    }
+
     fn from_comments(comments: Vec<CommentView>) -> Vec<CommentWithChildren> {
         let mut value: HashMap<i32, Vec<CommentView>> = comments
             .into_iter()
@@ -82,9 +152,37 @@ impl CommentWithChildren {
     }
 }
 
+/// A comment, or a reply of ours the server doesn't have yet
+#[component]
+fn ThreadEntry(
+    cx: Scope,
+    comment: CommentWithChildren,
+    thread: RwSignal<Vec<CommentWithChildren>>,
+    #[prop(optional)] depth: usize,
+) -> impl IntoView {
+    let id = comment.0.comment.id;
+    if PendingReplies::is_placeholder(id) {
+        view! { cx, <PendingReplyView id thread/> }.into_view(cx)
+    } else {
+        view! { cx, <Comment comment thread depth/> }.into_view(cx)
+    }
+}
+
 #[component]
-fn Comment(cx: Scope, comment: CommentWithChildren) -> impl IntoView {
-    let CommentWithChildren(comment, children) = comment;
+fn Comment(
+    cx: Scope,
+    comment: CommentWithChildren,
+    /// the whole thread, replies are read from it so they survive this row being scrolled away
+    thread: RwSignal<Vec<CommentWithChildren>>,
+    #[prop(optional)] depth: usize,
+) -> impl IntoView {
+    let CommentWithChildren(comment, _) = comment;
+    let fetched_state = CommentState::from(&comment);
+    let filters = use_context::<ContentFilters>(cx).unwrap();
+    let account = use_context::<Account>(cx).unwrap();
+    // hidden comments never get this far, see the children below
+    let filtered = cx.untrack(|| filters.comment(&comment));
+    let filter_collapsed = filtered.is_some();
     let CommentView {
         comment,
         creator,
@@ -94,7 +192,7 @@ fn Comment(cx: Scope, comment: CommentWithChildren) -> impl IntoView {
         creator_banned_from_community,
         subscribed,
         saved,
-        creator_blocked,
+        creator_blocked: _,
         my_vote,
     } = comment;
     let Comment {
@@ -112,7 +210,6 @@ fn Comment(cx: Scope, comment: CommentWithChildren) -> impl IntoView {
         distinguished,
         language_id,
     } = comment;
-    let (collapsed, set_collapsed) = create_signal(cx, false);
     let CommentAggregates {
         id,
         comment_id,
@@ -123,57 +220,112 @@ fn Comment(cx: Scope, comment: CommentWithChildren) -> impl IntoView {
         child_count,
         hot_rank,
     } = counts;
-    // let subscribed = create_rw_signal(cx, subscribed);
-    let my_vote = create_rw_signal(cx, my_vote);
-    let user = use_context::<CurrentUser>(cx).unwrap();
-    let (saved, set_saved) = create_signal(cx, saved);
-    create_effect(cx, move |prev| {
-        let vote = my_vote();
-        let user = user();
-        if let Some((Some(prev_user), prev)) = prev {
-            if Some(prev_user) == user && vote != prev {
-                spawn_local(async move {
-                    let client = use_context::<CapyClient>(cx).unwrap();
-                    let like = CreateCommentLike {
-                        comment_id,
-                        score: vote.unwrap_or_default(),
-                        ..Default::default()
-                    };
-                    // TODO: Actually update the comment somehow?
-                    let _ = client.execute(like).await;
-                    info!("liked comment");
+    let collapse_state = use_context::<CollapseState>(cx).unwrap();
+    let blocks = use_context::<Blocks>(cx).unwrap();
+    let collapsed = create_memo(cx, move |_| {
+        collapse_state
+            .overrides
+            .with(|o| o.get(&comment_id.0).copied())
+            .unwrap_or_else(|| {
+                filter_collapsed
+                    || collapse_state
+                        .rules
+                        .with(|r| r.should_collapse(score, depth))
+                    || (depth == 0 && collapse_state.collapse_top_level())
+            })
+    });
+    let set_collapsed = move |value: bool| {
+        collapse_state.overrides.update(|o| {
+            o.insert(comment_id.0, value);
+        });
+    };
+    if depth == 0 {
+        // "collapse all" should win over anything the user toggled by hand before
+        create_effect(cx, move |prev: Option<bool>| {
+            let collapse_all = collapse_state.collapse_top_level();
+            if prev.is_some() {
+                collapse_state.overrides.update(|o| {
+                    o.remove(&comment_id.0);
                 });
             }
-        }
-        (user, vote)
+            collapse_all
+        });
+    }
+    // let subscribed = create_rw_signal(cx, subscribed);
+    let store = use_context::<ItemStore>(cx).unwrap();
+    let state = store.state(cx, comment_id, fetched_state);
+    let on_vote = move |vote: Option<i16>| {
+        let like = CreateCommentLike {
+            comment_id,
+            score: vote.unwrap_or_default(),
+            ..Default::default()
+        };
+        store.mutate(
+            cx,
+            comment_id,
+            state.get_untracked(),
+            |s| s.vote(vote),
+            like,
+        );
+    };
+    let on_save = move |save: bool| {
+        let request = SaveComment {
+            comment_id,
+            save,
+            ..Default::default()
+        };
+        store.mutate(
+            cx,
+            comment_id,
+            state.get_untracked(),
+            |s| s.saved = save,
+            request,
+        );
+    };
+    let children = create_memo(cx, move |_| {
+        thread.with(|t| {
+            CommentWithChildren::find(t, comment_id)
+                .map(|c| c.1.clone())
+                .unwrap_or_default()
+        })
     });
-    create_effect(cx, move |prev| {
-        let saved = saved();
-        let user = user();
-        if let Some((Some(prev_user), prev)) = prev {
-            if Some(prev_user) == user && saved != prev {
-                spawn_local(async move {
-                    let client = use_context::<CapyClient>(cx).unwrap();
-                    let like = SaveComment {
-                        comment_id,
-                        save: saved,
-                        ..Default::default()
-                    };
-                    let _ = client.execute(like).await;
-                });
+    let open_replies = use_context::<OpenReplies>(cx).unwrap();
+    let (reply, set_reply) =
+        create_signal(cx, open_replies.0.with_value(|o| o.contains(&comment_id)));
+    create_effect(cx, move |_| {
+        let open = reply();
+        open_replies.0.update_value(|o| {
+            if open {
+                o.insert(comment_id);
+            } else {
+                o.remove(&comment_id);
             }
-        }
-        (user, saved)
+        });
     });
-    let children = create_rw_signal(cx, children);
-    let (reply, set_reply) = create_signal(cx, false);
+    let (quote, set_quote) = create_signal(cx, None::<String>);
+    let quote_content = content.clone();
     view! { cx,
-        <div class="flex flex-row border-neutral-700 hover:border-neutral-600 border-solid border-t-2">
+        <div
+            class="flex flex-row border-neutral-700 hover:border-neutral-600 border-solid border-t-2"
+            class:top-level-comment=depth == 0
+        >
             <button
-                class="p-1 bg-red-300 hover:bg-red-600 border-1 border-gray-200"
+                class=format!("p-1 border-1 border-gray-200 {}", DEPTH_COLORS[depth % DEPTH_COLORS.len()])
                 on:click=move |_| { set_collapsed(!collapsed()) }
             ></button>
-            <Voter my_vote upvotes downvotes score/>
+            {filtered.map(|filter| view! { cx,
+                <div class="text-gray-500 p-1" class:hidden=move || !collapsed()>
+                    "filtered: "
+                    {filter.rule.describe()}
+                </div>
+            })}
+            <Voter
+                my_vote=Signal::derive(cx, move || state.with(|s| s.my_vote))
+                upvotes=Signal::derive(cx, move || state.with(|s| s.upvotes))
+                downvotes=Signal::derive(cx, move || state.with(|s| s.downvotes))
+                score=Signal::derive(cx, move || state.with(|s| s.score))
+                on_vote
+            />
             <div class="flex flex-col grow transition" class:hidden=collapsed>
                 <div class="flex flex-row">
                     <div class="flex flex-row text-gray-500">
@@ -194,16 +346,44 @@ fn Comment(cx: Scope, comment: CommentWithChildren) -> impl IntoView {
                 </div>
                 <div class="flex flex-row gap-1 p-1">
                     <ReplyButton reply set_reply />
-                    <SaveButton saved set_saved/>
+                    <button
+                        class="flex flex-row bold text-gray-500 hover:text-gray-400 leading-none"
+                        on:click=move |_| {
+                            // quote whatever part of the comment is highlighted, or all of it
+                            let selected = window()
+                                .get_selection()
+                                .ok()
+                                .flatten()
+                                .map(|s| String::from(s.to_string()))
+                                .filter(|s| !s.trim().is_empty());
+                            set_quote(Some(selected.unwrap_or_else(|| quote_content.clone())));
+                        }
+                    >
+                        "quote"
+                    </button>
+                    <SaveButton saved=Signal::derive(cx, move || state.with(|s| s.saved)) on_save/>
+                    <ShareMenu targets=move || vec![
+                        ("permalink", instance_link(cx, &format!("/comment/{}", comment_id.0))),
+                        ("original link", ap_id.to_string()),
+                        ("app link", app_link(&ap_id)),
+                    ]/>
                 </div>
-                <ReplyBox post_id parent_id=Some(comment_id) reply set_reply children />
+                <ReplyBox post_id parent_id=Some(comment_id) reply set_reply thread quote />
                 <div class="">
-                    {move || children()
-                        .into_iter()
-                        .map(|comment| {
-                            view! { cx, <Comment comment/> }
-                        })
-                        .collect::<Vec<_>>()}
+                    <For
+                        each=move || children()
+                            .into_iter()
+                            .filter(|comment| {
+                                !filters.hides_comment(&comment.0)
+                                    && !blocks.hides_comment(&comment.0)
+                                    && !account.hides_creator(&comment.0.creator)
+                            })
+                            .collect::<Vec<_>>()
+                        key=|comment| comment.0.comment.id
+                        view=move |cx, comment| {
+                            view! { cx, <ThreadEntry comment thread depth=depth + 1/> }
+                        }
+                    />
                 </div>
             </div>
         </div>
@@ -211,9 +391,99 @@ fn Comment(cx: Scope, comment: CommentWithChildren) -> impl IntoView {
 }
 
 #[component]
-pub fn PostComments(cx: Scope, post_id: PostId) -> impl IntoView {
+fn CollapseControls(cx: Scope) -> impl IntoView {
+    let collapse_state = use_context::<CollapseState>(cx).unwrap();
+    let CollapseState {
+        rules,
+        collapse_top_level,
+        ..
+    } = collapse_state;
+    view! { cx,
+        <div class="flex flex-row gap-2 p-1 leading-none">
+            <button
+                class="bg-neutral-800 hover:bg-neutral-500 p-1 rounded"
+                on:click=move |_| collapse_top_level.update(|c| *c = !*c)
+            >
+                {move || if collapse_top_level() { "expand all" } else { "collapse all" }}
+            </button>
+            <label for="collapsescore">"collapse below score:"</label>
+            <input
+                type="number"
+                id="collapsescore"
+                class="w-16 bg-neutral-700 rounded"
+                prop:value=move || rules.with(|r| r.min_score.map(|s| s.to_string()).unwrap_or_default())
+                on:change=move |e| {
+                    let value = event_target_value(&e).parse().ok();
+                    rules.update(|r| r.min_score = value);
+                }
+            />
+            <label for="collapsedepth">"collapse deeper than:"</label>
+            <input
+                type="number"
+                id="collapsedepth"
+                min="0"
+                class="w-16 bg-neutral-700 rounded"
+                prop:value=move || rules.with(|r| r.max_depth.map(|d| d.to_string()).unwrap_or_default())
+                on:change=move |e| {
+                    let value = event_target_value(&e).parse().ok();
+                    rules.update(|r| r.max_depth = value);
+                }
+            />
+        </div>
+    }
+}
+
+/// Floating button that jumps to the next top level comment below the top of the screen
+#[component]
+fn NextTopLevelButton(cx: Scope) -> impl IntoView {
+    view! { cx,
+        <button
+            class="bg-gray-700 px-2 text-lg fixed bottom-20 right-20 rounded-md hover:bg-gray-400 z-50"
+            on:click=move |_| {
+                let Ok(comments) = document().query_selector_all(".top-level-comment") else {
+                    return;
+                };
+                // leave some room for the sticky navigation bar
+                let offset = 60.0;
+                let next = (0..comments.length())
+                    .filter_map(|i| comments.item(i))
+                    .filter_map(|node| node.dyn_into::<web_sys::Element>().ok())
+                    .map(|element| element.get_bounding_client_rect().top())
+                    .find(|top| *top > offset + 1.0);
+                if let Some(top) = next {
+                    window().scroll_by_with_x_and_y(0.0, top - offset);
+                }
+            }
+        >
+            "Next comment"
+        </button>
+    }
+}
+
+#[component]
+pub fn PostComments(
+    cx: Scope,
+    post_id: PostId,
+    /// the post being replied to, None until it has loaded
+    #[prop(into)]
+    post: Signal<Option<PostView>>,
+) -> impl IntoView {
     let (sort, set_sort) = create_signal(cx, Some(CommentSortType::Hot));
-    let limit = Some(50);
+    let limit = Some(COMMENT_PAGE_SIZE);
+    let collapse_state = CollapseState {
+        overrides: create_rw_signal(cx, Settings::collapsed_comments(post_id)),
+        rules: create_rw_signal(cx, Settings::collapse_rules()),
+        collapse_top_level: create_rw_signal(cx, false),
+    };
+    provide_context(cx, collapse_state);
+    provide_context(cx, OpenReplies(store_value(cx, HashSet::new())));
+    provide_context(cx, PendingReplies::new(cx, post_id, post));
+    create_effect(cx, move |_| {
+        Settings::set_collapsed_comments(post_id, collapse_state.overrides());
+    });
+    create_effect(cx, move |_| {
+        Settings::set_collapse_rules(collapse_state.rules());
+    });
     let post_comments = create_resource(
         cx,
         move || sort(),
@@ -237,6 +507,7 @@ pub fn PostComments(cx: Scope, post_id: PostId) -> impl IntoView {
         <div class="flex flex-row">
             <CommentSortMenu sort set_sort/>
             <ReplyButton reply set_reply />
+            <CollapseControls />
         </div>
         <Suspense fallback=move || {
             view! { cx, "Loading" }
@@ -247,11 +518,25 @@ pub fn PostComments(cx: Scope, post_id: PostId) -> impl IntoView {
                     .map(|comments| {
                         let comments = CommentWithChildren::from_comments(comments.comments);
                         let comments = create_rw_signal(cx, comments);
+                        // only worth showing the jump button on longer threads
+                        let long_thread = move || comments.with(|c| c.len() > 5);
+                        let filters = use_context::<ContentFilters>(cx).unwrap();
+                        let account = use_context::<Account>(cx).unwrap();
+                        let blocks = use_context::<Blocks>(cx).unwrap();
                         view! { cx,
-                            <ReplyBox reply set_reply post_id parent_id=None children=comments />
+                            {move || long_thread().then(|| view! { cx, <NextTopLevelButton/> })}
+                            <ReplyBox reply set_reply post_id parent_id=None thread=comments />
                             <InfinitePage
-                                view=move |cx, comment| {
-                                    view! { cx, <Comment comment=comment/> }
+                                view=move |cx, comment: CommentWithChildren| {
+                                    let hidden = create_memo(cx, {
+                                        let comment = comment.0.clone();
+                                        move |_| {
+                                            filters.hides_comment(&comment)
+                                                || blocks.hides_comment(&comment)
+                                                || account.hides_creator(&comment.creator)
+                                        }
+                                    });
+                                    move || (!hidden()).then(|| view! { cx, <ThreadEntry comment=comment.clone() thread=comments/> })
                                 }
                                 get_page=move |p| {
                                     async move {
@@ -260,7 +545,7 @@ pub fn PostComments(cx: Scope, post_id: PostId) -> impl IntoView {
                                             .execute(GetComments {
                                                 post_id: Some(post_id),
                                                 sort: sort.get_untracked(),
-                                                limit: None,
+                                                limit,
                                                 page: Some(p as i64),
                                                 ..Default::default()
                                             })
@@ -2,7 +2,15 @@ use capybara_lemmy_client::community::{Community, SubscribedType};
 use leptos::*;
 use leptos_use::{use_element_hover_with_options, UseElementHoverOptions};
 
-use crate::components::{markdown::Markdown, subscribe::SubscribeButton, time::RelativeTime};
+use crate::{
+    account::Account,
+    app::CurrentUser,
+    blocks::Blocks,
+    components::{
+        image_proxy::ImageProxy, markdown::Markdown, subscribe::SubscribeButton, time::RelativeTime,
+    },
+    nsfw::{Nsfw, NsfwPolicy, NsfwPolicySelect},
+};
 
 #[component]
 pub fn CommunityBadge(
@@ -10,6 +18,17 @@ pub fn CommunityBadge(
     community: Community,
     subscribed: RwSignal<SubscribedType>,
 ) -> impl IntoView {
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    let blocks = use_context::<Blocks>(cx).unwrap();
+    let proxy = use_context::<ImageProxy>(cx).unwrap();
+    let nsfw_policies = use_context::<Nsfw>(cx).unwrap();
+    let account = use_context::<Account>(cx).unwrap();
+    let block_community = community.clone();
+    let policy_community = community.clone();
+    let blur = {
+        let community = community.clone();
+        move || nsfw_policies.blurs_community(&community)
+    };
     let Community {
         id,
         name,
@@ -55,16 +74,27 @@ pub fn CommunityBadge(
     view! {cx,
         <div class="relative">
             <a href=format!("/c/{}", id.0) class="flex flex-row underline text-red-400 hover:text-red-600" class:font-bold=nsfw node_ref=group_link>
-            {icon.as_ref().map(|icon| view!{cx, <img class="rounded w-6 h-6" src=icon.to_string()/>})}
+            {icon.as_ref().map(|icon| {
+                let icon = icon.to_string();
+                let blur = blur.clone();
+                view!{cx, <img class="rounded w-6 h-6" class:blur=blur class:hidden=move || !account.show_avatars() src=move || proxy.src(&icon, Some(48))/>}
+            })}
             {name}
             {(!local).then(|| view!{cx, <div class="italic">"@"{actor_id.host_str().unwrap_or_default().to_string()}</div>})}
 
             </a>
             <div class="flex-col absolute top-10 left-10 p-5 z-10 w-96 h-96 overflow-y-auto bg-neutral-800 rounded-xl" class:hidden=move || { !(hovered() || group_hover()) } node_ref=popup>
                 <div class="flex flex-col">
-                    {banner.map(|b| view!{cx, <img src=b.to_string() class="h-fit w-96"/>})}
+                    {banner.map(|b| {
+                        let b = b.to_string();
+                        let blur = blur.clone();
+                        view!{cx, <img src=move || proxy.src(&b, Some(768)) class="h-fit w-96" class:blur=blur/>}
+                    })}
                     <div class="flex flex-row">
-                        {icon.map(|icon| view!{cx, <img class="rounded w-12 h-12" src=icon.to_string()/>})}
+                        {icon.map(|icon| {
+                            let icon = icon.to_string();
+                            view!{cx, <img class="rounded w-12 h-12" class:blur=blur src=move || proxy.src(&icon, Some(96))/>}
+                        })}
                         <div class="flex flex-col">
                             <div class="flex flex-row">
                                 <div class="text-lg">{title}</div>
@@ -79,7 +109,34 @@ pub fn CommunityBadge(
                             </div>
                         </div>
                     </div>
-                    <SubscribeButton community_id=id subscribed />
+                    <div class="flex flex-row gap-2">
+                        <SubscribeButton community_id=id subscribed />
+                        {move || current_user.with(|u| u.is_some()).then(|| {
+                            let block_community = block_community.clone();
+                            view!{cx,
+                                <button
+                                    class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                                    on:click=move |_| {
+                                        let blocked = blocks.is_community_blocked(id);
+                                        blocks.block_community(cx, block_community.clone(), !blocked);
+                                    }
+                                >
+                                    {move || if blocks.is_community_blocked(id) { "unblock community" } else { "block community" }}
+                                </button>
+                            }
+                        })}
+                    </div>
+                    <div class="flex flex-row gap-2">
+                        "nsfw:"
+                        <NsfwPolicySelect
+                            policy=Signal::derive(cx, {
+                                let community = policy_community.clone();
+                                move || nsfw_policies.community_policy(&community)
+                            })
+                            on_change=move |policy: Option<NsfwPolicy>| nsfw_policies.set_community_policy(&policy_community, policy)
+                            inherit="default"
+                        />
+                    </div>
                     {description.map(|description| view!{cx, <Markdown content=description />})}
                 </div>
             </div>
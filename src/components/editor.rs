@@ -0,0 +1,333 @@
+use leptos::{html::Textarea, *};
+
+use crate::components::markdown::Markdown;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Bold,
+    Italic,
+    Link,
+    Quote,
+    Code,
+    Spoiler,
+    List,
+}
+
+impl Format {
+    fn label(&self) -> &'static str {
+        match self {
+            Format::Bold => "B",
+            Format::Italic => "I",
+            Format::Link => "link",
+            Format::Quote => "quote",
+            Format::Code => "code",
+            Format::Spoiler => "spoiler",
+            Format::List => "list",
+        }
+    }
+
+    /// Applies the formatting to the selected byte range of `text`, returning the new text and
+    /// the byte range that should be selected afterwards.
+    fn apply(&self, text: &str, start: usize, end: usize) -> (String, usize, usize) {
+        let (before, selected, after) = (&text[..start], &text[start..end], &text[end..]);
+        // block level formatting needs to start on its own line
+        let newline = if before.is_empty() || before.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        match self {
+            Format::Bold => wrap(before, selected, after, "**", "**", "bold text"),
+            Format::Italic => wrap(before, selected, after, "*", "*", "italic text"),
+            Format::Code if selected.contains('\n') => wrap(
+                before,
+                selected,
+                after,
+                &format!("{newline}```\n"),
+                "\n```\n",
+                "",
+            ),
+            Format::Code => wrap(before, selected, after, "`", "`", "code"),
+            Format::Spoiler => wrap(
+                before,
+                selected,
+                after,
+                &format!("{newline}::: spoiler spoiler\n"),
+                "\n:::\n",
+                "hidden text",
+            ),
+            Format::Link => {
+                let label = if selected.is_empty() {
+                    "link text"
+                } else {
+                    selected
+                };
+                let link_start = before.len() + label.len() + 3;
+                (
+                    format!("{before}[{label}](url){after}"),
+                    link_start,
+                    link_start + 3,
+                )
+            }
+            Format::Quote => prefix_lines(text, start, end, "> "),
+            Format::List => prefix_lines(text, start, end, "- "),
+        }
+    }
+}
+
+fn wrap(
+    before: &str,
+    selected: &str,
+    after: &str,
+    prefix: &str,
+    suffix: &str,
+    placeholder: &str,
+) -> (String, usize, usize) {
+    let selected = if selected.is_empty() {
+        placeholder
+    } else {
+        selected
+    };
+    let start = before.len() + prefix.len();
+    (
+        format!("{before}{prefix}{selected}{suffix}{after}"),
+        start,
+        start + selected.len(),
+    )
+}
+
+fn prefix_lines(text: &str, start: usize, end: usize, prefix: &str) -> (String, usize, usize) {
+    let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let prefixed = text[line_start..end]
+        .split('\n')
+        .map(|line| format!("{prefix}{line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let new_end = line_start + prefixed.len();
+    (
+        format!("{}{prefixed}{}", &text[..line_start], &text[end..]),
+        line_start,
+        new_end,
+    )
+}
+
+/// Turns text into a markdown quote, ready to be replied under
+pub fn quote_markdown(text: &str) -> String {
+    let quoted = text
+        .trim()
+        .lines()
+        .map(|line| format!("> {line}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{quoted}\n\n")
+}
+
+/// textarea selections are in utf-16 code units, rust strings are indexed by bytes
+fn byte_index(text: &str, utf16_index: u32) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= utf16_index as usize {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn utf16_index(text: &str, byte_index: usize) -> u32 {
+    text[..byte_index].encode_utf16().count() as u32
+}
+
+/// Markdown text box with a formatting toolbar and a preview tab. Only replies use it so far,
+/// posts and messages can't be written in the app yet.
+#[component]
+pub fn MarkdownEditor<I, S>(
+    cx: Scope,
+    content: ReadSignal<String>,
+    /// Called with the full text whenever it changes
+    on_input: I,
+    /// Called by ctrl+enter or the submit button
+    on_submit: S,
+    #[prop(optional, into)] disabled: MaybeSignal<bool>,
+    /// in characters, lemmy's limits differ between comments, posts and bios
+    max_length: usize,
+    #[prop(default = "send")] submit_text: &'static str,
+) -> impl IntoView
+where
+    I: Fn(String) + Copy + 'static,
+    S: Fn() + Copy + 'static,
+{
+    let textarea = create_node_ref::<Textarea>(cx);
+    let (preview, set_preview) = create_signal(cx, false);
+    let length = move || content.with(|c| c.chars().count());
+    let too_long = move || length() > max_length;
+    let submit = move || {
+        if !too_long() && !disabled.get() && content.with(|c| !c.trim().is_empty()) {
+            on_submit();
+        }
+    };
+    let format = move |format: Format| {
+        let Some(textarea) = textarea.get() else {
+            return;
+        };
+        let text = content.get_untracked();
+        let start = textarea
+            .selection_start()
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let end = textarea.selection_end().ok().flatten().unwrap_or(start);
+        let (new_text, new_start, new_end) =
+            format.apply(&text, byte_index(&text, start), byte_index(&text, end));
+        let (new_start, new_end) = (
+            utf16_index(&new_text, new_start),
+            utf16_index(&new_text, new_end),
+        );
+        on_input(new_text);
+        // wait for the new value to land in the textarea before moving the selection
+        request_animation_frame(move || {
+            let _ = textarea.focus();
+            let _ = textarea.set_selection_range(new_start, new_end);
+        });
+    };
+    let tab_class = move |selected: bool| {
+        if selected {
+            "px-2 rounded-t bg-neutral-700"
+        } else {
+            "px-2 rounded-t bg-neutral-800 hover:bg-neutral-600"
+        }
+    };
+    view! { cx,
+        <div class="flex flex-col m-4 w-[calc(100%-30px)]">
+            <div class="flex flex-row gap-1 leading-none">
+                <button class=move || tab_class(!preview()) on:click=move |_| set_preview(false)>"write"</button>
+                <button class=move || tab_class(preview()) on:click=move |_| set_preview(true)>"preview"</button>
+                <div class="flex flex-row gap-1 pl-4" class:hidden=preview>
+                    {[
+                        Format::Bold,
+                        Format::Italic,
+                        Format::Link,
+                        Format::Quote,
+                        Format::Code,
+                        Format::Spoiler,
+                        Format::List,
+                    ]
+                        .into_iter()
+                        .map(|f| {
+                            view! { cx,
+                                <button
+                                    class="bg-neutral-800 hover:bg-neutral-500 px-1 rounded"
+                                    class:font-bold=f == Format::Bold
+                                    class:italic=f == Format::Italic
+                                    on:click=move |_| format(f)
+                                >
+                                    {f.label()}
+                                </button>
+                            }
+                        })
+                        .collect::<Vec<_>>()}
+                </div>
+            </div>
+            <textarea
+                node_ref=textarea
+                class="h-36 rounded ring inset-2 ring-neutral-700 focus:ring-neutral-500 bg-neutral-700 text-neutral-100 p-4"
+                class:hidden=preview
+                prop:value=content
+                prop:disabled=move || disabled.get()
+                on:input=move |i| on_input(event_target_value(&i))
+                on:keydown=move |e| {
+                    if e.key() == "Enter" && (e.ctrl_key() || e.meta_key()) {
+                        e.prevent_default();
+                        submit();
+                    }
+                }
+            ></textarea>
+            <div class="min-h-[9rem] bg-neutral-800 rounded p-4" class:hidden=move || !preview()>
+                {move || preview().then(|| view! { cx, <Markdown content=content()/> })}
+            </div>
+            <div class="flex flex-row gap-2 pt-1">
+                <button
+                    class="bg-gray-600 p-1 rounded hover:bg-gray-300"
+                    prop:disabled=move || too_long() || disabled.get()
+                    on:click=move |_| submit()
+                >
+                    {submit_text}
+                </button>
+                <div class=move || if too_long() { "text-red-500" } else { "text-gray-500" }>
+                    {length} "/" {max_length}
+                </div>
+                <div class="text-gray-500">"ctrl+enter to send"</div>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_empty_selection_in_placeholder() {
+        let (text, start, end) = Format::Bold.apply("ab", 1, 1);
+        assert_eq!(text, "a**bold text**b");
+        assert_eq!(&text[start..end], "bold text");
+    }
+
+    #[test]
+    fn wraps_selection() {
+        let (text, start, end) = Format::Italic.apply("say hi", 4, 6);
+        assert_eq!(text, "say *hi*");
+        assert_eq!(&text[start..end], "hi");
+    }
+
+    #[test]
+    fn code_block_starts_on_its_own_line() {
+        let (text, start, end) = Format::Code.apply("see a\nb", 4, 7);
+        assert_eq!(text, "see \n```\na\nb\n```\n");
+        assert_eq!(&text[start..end], "a\nb");
+    }
+
+    #[test]
+    fn link_selects_url() {
+        let (text, start, end) = Format::Link.apply("read this", 5, 9);
+        assert_eq!(text, "read [this](url)");
+        assert_eq!(&text[start..end], "url");
+    }
+
+    #[test]
+    fn list_prefixes_every_selected_line() {
+        let (text, start, end) = Format::List.apply("one\ntwo\nthree", 1, 7);
+        assert_eq!(text, "- one\n- two\nthree");
+        assert_eq!(&text[start..end], "- one\n- two");
+    }
+
+    #[test]
+    fn quote_starts_at_beginning_of_line() {
+        let (text, start, end) = Format::Quote.apply("say\nhi there", 6, 6);
+        assert_eq!(text, "say\n> hi there");
+        assert_eq!(&text[start..end], "> hi");
+    }
+
+    #[test]
+    fn utf16_offsets_round_trip() {
+        // é is one utf-16 unit and two bytes, the emoji is two units and four bytes
+        let text = "é😀a";
+        assert_eq!(byte_index(text, 1), 2);
+        assert_eq!(byte_index(text, 3), 6);
+        assert_eq!(byte_index(text, 10), text.len());
+        assert_eq!(utf16_index(text, 6), 3);
+    }
+
+    #[test]
+    fn formats_non_ascii_selection() {
+        let text = "naïve café";
+        let (start, end) = (byte_index(text, 6), byte_index(text, 10));
+        let (text, start, end) = Format::Bold.apply(text, start, end);
+        assert_eq!(text, "naïve **café**");
+        assert_eq!(&text[start..end], "café");
+        assert_eq!(
+            (utf16_index(&text, start), utf16_index(&text, end)),
+            (8, 12)
+        );
+    }
+}
@@ -0,0 +1,183 @@
+use chrono::Utc;
+use gloo::storage::{SessionStorage, Storage};
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Bump whenever the shape of cached feeds or their items changes, old entries get dropped
+const CACHE_VERSION: u32 = 2;
+/// Total size of all cached feeds, in characters of serialized json
+const MAX_CACHE_SIZE: usize = 2_000_000;
+/// Feeds longer than this only keep their first items
+const MAX_CACHED_ITEMS: usize = 300;
+/// Cached feeds older than this are thrown away
+const CACHE_TTL_MS: i64 = 60 * 60 * 1000;
+/// Cached feeds older than this get checked for new items when they're restored
+const STALE_AFTER_MS: i64 = 5 * 60 * 1000;
+
+const INDEX_KEY: &str = "feed_cache_index";
+
+#[derive(Serialize, Deserialize)]
+pub struct CachedFeed<T> {
+    version: u32,
+    saved_at: i64,
+    pub page: usize,
+    pub y_scroll: f64,
+    pub data: Vec<T>,
+    /// measured height of each row in `data`, so the scroll position can be restored before
+    /// the rows have been rendered again
+    pub heights: Vec<Option<f64>>,
+    /// the page each row in `data` was fetched as
+    pub pages: Vec<Option<usize>>,
+}
+
+impl<T> CachedFeed<T> {
+    pub fn new(
+        page: usize,
+        y_scroll: f64,
+        data: Vec<T>,
+        heights: Vec<Option<f64>>,
+        pages: Vec<Option<usize>>,
+    ) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            saved_at: Utc::now().timestamp_millis(),
+            page,
+            y_scroll,
+            data,
+            heights,
+            pages,
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        Utc::now().timestamp_millis() - self.saved_at > STALE_AFTER_MS
+    }
+
+    fn is_expired(&self) -> bool {
+        self.version != CACHE_VERSION
+            || Utc::now().timestamp_millis() - self.saved_at > CACHE_TTL_MS
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexEntry {
+    key: String,
+    size: usize,
+}
+
+/// Feeds saved away when they're closed so they can be restored with their scroll position.
+/// Entries live in session storage and are evicted least recently used first.
+pub struct FeedCache;
+
+impl FeedCache {
+    /// Most recently used first
+    fn index() -> Vec<IndexEntry> {
+        SessionStorage::get(INDEX_KEY).ok().unwrap_or_default()
+    }
+
+    fn set_index(index: &[IndexEntry]) {
+        let _ = SessionStorage::set(INDEX_KEY, index);
+    }
+
+    pub fn load<T>(key: &str) -> Option<CachedFeed<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let mut index = Self::index();
+        let position = index.iter().position(|e| e.key == key)?;
+        let entry = index.remove(position);
+        match SessionStorage::get::<CachedFeed<T>>(key) {
+            Ok(feed) if !feed.is_expired() => {
+                index.insert(0, entry);
+                Self::set_index(&index);
+                Some(feed)
+            }
+            _ => {
+                info!("dropping expired or outdated feed cache {key}");
+                SessionStorage::delete(key);
+                Self::set_index(&index);
+                None
+            }
+        }
+    }
+
+    pub fn save<T>(key: &str, mut feed: CachedFeed<T>)
+    where
+        T: Serialize,
+    {
+        if feed.data.len() > MAX_CACHED_ITEMS {
+            // keep loading from about where the kept items end, duplicates get filtered out
+            feed.page = (feed.page * MAX_CACHED_ITEMS / feed.data.len()).max(1);
+            feed.data.truncate(MAX_CACHED_ITEMS);
+        }
+        feed.heights.truncate(MAX_CACHED_ITEMS);
+        feed.pages.truncate(MAX_CACHED_ITEMS);
+        let Ok(json) = serde_json::to_string(&feed) else {
+            return;
+        };
+        let mut index = Self::index();
+        index.retain(|e| e.key != key);
+        SessionStorage::delete(key);
+        if json.len() > MAX_CACHE_SIZE {
+            Self::set_index(&index);
+            return;
+        }
+        while index.iter().map(|e| e.size).sum::<usize>() + json.len() > MAX_CACHE_SIZE {
+            let Some(evicted) = index.pop() else {
+                break;
+            };
+            SessionStorage::delete(&evicted.key);
+        }
+        let storage = SessionStorage::raw();
+        // other things in session storage can still push us over the quota, make room and retry
+        while storage.set_item(key, &json).is_err() {
+            let Some(evicted) = index.pop() else {
+                Self::set_index(&index);
+                return;
+            };
+            SessionStorage::delete(&evicted.key);
+        }
+        index.insert(
+            0,
+            IndexEntry {
+                key: key.to_string(),
+                size: json.len(),
+            },
+        );
+        Self::set_index(&index);
+    }
+
+    /// Runs `patch` over every item of every cached feed, saving the feeds where it returned
+    /// true for any item. Feeds that no longer fit are dropped rather than left out of date.
+    pub fn patch<T>(patch: impl Fn(&mut T) -> bool)
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        let mut index = Self::index();
+        let storage = SessionStorage::raw();
+        index.retain_mut(|entry| {
+            // other feeds hold different item types and just fail to deserialize
+            let Ok(mut cached) = SessionStorage::get::<CachedFeed<T>>(&entry.key) else {
+                return true;
+            };
+            let changed = cached
+                .data
+                .iter_mut()
+                .fold(false, |changed, item| patch(item) || changed);
+            if !changed {
+                return true;
+            }
+            match serde_json::to_string(&cached) {
+                Ok(json) if storage.set_item(&entry.key, &json).is_ok() => {
+                    entry.size = json.len();
+                    true
+                }
+                _ => {
+                    SessionStorage::delete(&entry.key);
+                    false
+                }
+            }
+        });
+        Self::set_index(&index);
+    }
+}
@@ -0,0 +1,704 @@
+use capybara_lemmy_client::post::PostId;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{
+    media::{self, MediaKind},
+    video::VideoPlayer,
+};
+
+/// What a provider turned a link into
+#[derive(Clone, PartialEq, Debug)]
+pub enum Embed {
+    /// a player page from the provider
+    Iframe(String),
+    /// a video file that can be played directly
+    Video(String),
+}
+
+/// Turns links to a site into something that can be shown inline
+pub trait EmbedProvider: Sync {
+    /// Stable id, used to remember whether the provider is turned off
+    fn id(&self) -> &'static str;
+    fn name(&self) -> &'static str;
+    /// The embed for `url`, if this provider handles it
+    fn embed(&self, url: &Url, settings: &EmbedSettings) -> Option<Embed>;
+}
+
+/// Where YouTube videos are played from
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum YoutubeFrontend {
+    /// youtube-nocookie.com, doesn't set cookies until the video is played
+    NoCookie,
+    Invidious(String),
+    Piped(String),
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbedSettings {
+    /// ids of providers that shouldn't be embedded
+    pub disabled: Vec<String>,
+    /// show a button instead of loading third party pages right away
+    pub click_to_load: bool,
+    pub youtube: YoutubeFrontend,
+    pub nitter_instance: String,
+    /// PeerTube is on any host, only these are embedded
+    pub peertube_instances: Vec<String>,
+}
+
+impl Default for EmbedSettings {
+    fn default() -> Self {
+        Self {
+            disabled: vec![],
+            click_to_load: false,
+            youtube: YoutubeFrontend::NoCookie,
+            nitter_instance: "nitter.net".to_string(),
+            peertube_instances: ["framatube.org", "tilvids.com", "video.blender.org"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+impl EmbedSettings {
+    pub fn is_enabled(&self, provider: &dyn EmbedProvider) -> bool {
+        !self.disabled.iter().any(|id| id == provider.id())
+    }
+}
+
+/// The embed settings, saved with the rest of the preferences
+#[derive(Clone, Copy)]
+pub struct Embeds(pub RwSignal<EmbedSettings>);
+
+impl Embeds {
+    pub fn new(cx: Scope, settings: EmbedSettings) -> Self {
+        Self(create_rw_signal(cx, settings))
+    }
+
+    pub fn is_enabled(&self, provider: &dyn EmbedProvider) -> bool {
+        self.0.with(|s| s.is_enabled(provider))
+    }
+}
+
+/// `url`'s host without a leading www.
+fn host(url: &Url) -> &str {
+    let host = url.host_str().unwrap_or_default();
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+fn segments(url: &Url) -> Vec<&str> {
+    url.path_segments()
+        .map(|s| s.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+const INVIDIOUS_HOSTS: [&str; 4] = [
+    "yewtu.be",
+    "inv.nadeko.net",
+    "invidious.nerdvpn.de",
+    "invidious.privacyredirect.com",
+];
+const PIPED_HOSTS: [&str; 3] = ["piped.video", "piped.kavin.rocks", "piped.adminforge.de"];
+
+/// YouTube, and Invidious and Piped instances
+pub struct YouTube;
+
+impl YouTube {
+    fn video_id(url: &Url) -> Option<String> {
+        let host = host(url);
+        let segments = segments(url);
+        let watch = || {
+            url.query_pairs()
+                .find(|(k, _)| k == "v")
+                .map(|(_, v)| v.to_string())
+        };
+        match (host, segments.as_slice()) {
+            ("youtu.be", [id]) => Some(id.to_string()),
+            (
+                "youtube.com" | "m.youtube.com" | "music.youtube.com" | "youtube-nocookie.com",
+                path,
+            ) => match path {
+                ["watch"] => watch(),
+                ["shorts" | "embed" | "live", id] => Some(id.to_string()),
+                _ => None,
+            },
+            (host, ["watch"]) if INVIDIOUS_HOSTS.contains(&host) || PIPED_HOSTS.contains(&host) => {
+                watch()
+            }
+            (host, ["embed", id])
+                if INVIDIOUS_HOSTS.contains(&host) || PIPED_HOSTS.contains(&host) =>
+            {
+                Some(id.to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl EmbedProvider for YouTube {
+    fn id(&self) -> &'static str {
+        "youtube"
+    }
+
+    fn name(&self) -> &'static str {
+        "YouTube"
+    }
+
+    fn embed(&self, url: &Url, settings: &EmbedSettings) -> Option<Embed> {
+        let id = Self::video_id(url)?;
+        let src = match &settings.youtube {
+            YoutubeFrontend::NoCookie => {
+                format!("https://www.youtube-nocookie.com/embed/{id}?autoplay=0")
+            }
+            YoutubeFrontend::Invidious(instance) => {
+                format!("https://{instance}/embed/{id}?autoplay=0")
+            }
+            YoutubeFrontend::Piped(instance) => format!("https://{instance}/embed/{id}"),
+        };
+        Some(Embed::Iframe(src))
+    }
+}
+
+/// Albums as an iframe, gifv links as the mp4 they really are
+pub struct Imgur;
+
+impl EmbedProvider for Imgur {
+    fn id(&self) -> &'static str {
+        "imgur"
+    }
+
+    fn name(&self) -> &'static str {
+        "Imgur"
+    }
+
+    fn embed(&self, url: &Url, _settings: &EmbedSettings) -> Option<Embed> {
+        match (host(url), segments(url).as_slice()) {
+            ("imgur.com", ["a" | "gallery", id]) => Some(Embed::Iframe(format!(
+                "https://imgur.com/a/{id}/embed?pub=true"
+            ))),
+            ("i.imgur.com", [file]) => file
+                .strip_suffix(".gifv")
+                .map(|id| Embed::Video(format!("https://i.imgur.com/{id}.mp4"))),
+            _ => None,
+        }
+    }
+}
+
+pub struct Streamable;
+
+impl EmbedProvider for Streamable {
+    fn id(&self) -> &'static str {
+        "streamable"
+    }
+
+    fn name(&self) -> &'static str {
+        "Streamable"
+    }
+
+    fn embed(&self, url: &Url, _settings: &EmbedSettings) -> Option<Embed> {
+        match (host(url), segments(url).as_slice()) {
+            ("streamable.com", [id] | ["e", id]) => {
+                Some(Embed::Iframe(format!("https://streamable.com/e/{id}")))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct Vimeo;
+
+impl EmbedProvider for Vimeo {
+    fn id(&self) -> &'static str {
+        "vimeo"
+    }
+
+    fn name(&self) -> &'static str {
+        "Vimeo"
+    }
+
+    fn embed(&self, url: &Url, _settings: &EmbedSettings) -> Option<Embed> {
+        let id = match (host(url), segments(url).as_slice()) {
+            ("vimeo.com", [id]) | ("player.vimeo.com", ["video", id]) => *id,
+            _ => return None,
+        };
+        // dnt keeps vimeo from tracking the session
+        id.chars()
+            .all(|c| c.is_ascii_digit())
+            .then(|| Embed::Iframe(format!("https://player.vimeo.com/video/{id}?dnt=1")))
+    }
+}
+
+/// Short looping clips, redgifs and gfycat
+pub struct Gifs;
+
+impl EmbedProvider for Gifs {
+    fn id(&self) -> &'static str {
+        "gifs"
+    }
+
+    fn name(&self) -> &'static str {
+        "RedGIFs and Gfycat"
+    }
+
+    fn embed(&self, url: &Url, _settings: &EmbedSettings) -> Option<Embed> {
+        match (host(url), segments(url).as_slice()) {
+            ("redgifs.com", ["watch" | "ifr", id]) => Some(Embed::Iframe(format!(
+                "https://www.redgifs.com/ifr/{}",
+                id.to_lowercase()
+            ))),
+            ("gfycat.com", [id] | ["ifr", id]) => {
+                Some(Embed::Iframe(format!("https://gfycat.com/ifr/{id}")))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Tweets, shown through a Nitter instance rather than twitter's own widget
+pub struct Twitter;
+
+impl EmbedProvider for Twitter {
+    fn id(&self) -> &'static str {
+        "twitter"
+    }
+
+    fn name(&self) -> &'static str {
+        "Twitter (through Nitter)"
+    }
+
+    fn embed(&self, url: &Url, settings: &EmbedSettings) -> Option<Embed> {
+        let host = host(url);
+        let twitter = matches!(host, "twitter.com" | "mobile.twitter.com" | "x.com");
+        if !twitter && host != settings.nitter_instance && host != "nitter.net" {
+            return None;
+        }
+        match segments(url).as_slice() {
+            [user, "status", id, ..] => Some(Embed::Iframe(format!(
+                "https://{}/{user}/status/{id}/embed",
+                settings.nitter_instance
+            ))),
+            _ => None,
+        }
+    }
+}
+
+/// PeerTube instances the user listed, anything else could be any site with a /w/ path
+pub struct PeerTube;
+
+impl EmbedProvider for PeerTube {
+    fn id(&self) -> &'static str {
+        "peertube"
+    }
+
+    fn name(&self) -> &'static str {
+        "PeerTube"
+    }
+
+    fn embed(&self, url: &Url, settings: &EmbedSettings) -> Option<Embed> {
+        let host = host(url);
+        if !settings.peertube_instances.iter().any(|i| i == host) {
+            return None;
+        }
+        let id = match segments(url).as_slice() {
+            ["w", id] | ["videos", "watch" | "embed", id] => *id,
+            _ => return None,
+        };
+        // short ids are 22 characters and long ones are uuids
+        let valid = id.len() >= 20 && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+        valid.then(|| {
+            Embed::Iframe(format!(
+                "https://{host}/videos/embed/{id}?peertubeLink=0&warningTitle=0"
+            ))
+        })
+    }
+}
+
+/// Reddit galleries and posts
+pub struct Reddit;
+
+impl EmbedProvider for Reddit {
+    fn id(&self) -> &'static str {
+        "reddit"
+    }
+
+    fn name(&self) -> &'static str {
+        "Reddit"
+    }
+
+    fn embed(&self, url: &Url, _settings: &EmbedSettings) -> Option<Embed> {
+        if !matches!(
+            host(url),
+            "reddit.com" | "old.reddit.com" | "new.reddit.com"
+        ) {
+            return None;
+        }
+        match segments(url).as_slice() {
+            ["gallery", id] => Some(Embed::Iframe(format!(
+                "https://embed.reddit.com/comments/{id}"
+            ))),
+            ["r", subreddit, "comments", id, ..] => Some(Embed::Iframe(format!(
+                "https://embed.reddit.com/r/{subreddit}/comments/{id}"
+            ))),
+            _ => None,
+        }
+    }
+}
+
+/// Links straight to video files
+pub struct Direct;
+
+impl EmbedProvider for Direct {
+    fn id(&self) -> &'static str {
+        "direct"
+    }
+
+    fn name(&self) -> &'static str {
+        "Video files"
+    }
+
+    fn embed(&self, url: &Url, _settings: &EmbedSettings) -> Option<Embed> {
+        let url = url.to_string();
+        (media::guess(&url) == Some(MediaKind::Video)).then(|| Embed::Video(url))
+    }
+}
+
+/// Every provider, the first one to handle a link wins
+pub static PROVIDERS: [&dyn EmbedProvider; 9] = [
+    &YouTube,
+    &Imgur,
+    &Streamable,
+    &Vimeo,
+    &Gifs,
+    &Twitter,
+    &Reddit,
+    &PeerTube,
+    &Direct,
+];
+
+/// The provider that handles `url` and what it embeds it as
+pub fn find(url: &Url, settings: &EmbedSettings) -> Option<(&'static dyn EmbedProvider, Embed)> {
+    PROVIDERS
+        .iter()
+        .find_map(|provider| Some((*provider, provider.embed(url, settings)?)))
+}
+
+/// The embed for a post, from its link or failing that the video url lemmy found for it
+#[component]
+pub fn PostEmbed(
+    cx: Scope,
+    url: Option<Url>,
+    embed_video_url: Option<Url>,
+    post_id: PostId,
+    /// the server said the link is a video, even though the url doesn't
+    #[prop(optional)]
+    detected_video: bool,
+) -> impl IntoView {
+    let embeds = use_context::<Embeds>(cx).unwrap();
+    let (loaded, set_loaded) = create_signal(cx, false);
+    move || {
+        let settings = embeds.0.get();
+        let found = [&url, &embed_video_url]
+            .into_iter()
+            .flatten()
+            .find_map(|url| find(url, &settings).map(|(p, e)| (p, e, url.to_string())));
+        let Some((provider, embed, link)) = found else {
+            if detected_video {
+                return url.as_ref().map(|src| {
+                    view! { cx, <VideoPlayer src=src.to_string() post_id/> }.into_view(cx)
+                });
+            }
+            // lemmy found a player we don't know, show it as is
+            return embed_video_url.as_ref().map(|src| {
+                view! { cx,
+                    <iframe lazy="true" sandbox="allow-scripts allow-same-origin" allowfullscreen
+                    frameborder="0" class="h-96 w-fit aspect-video" src=src.to_string()></iframe>
+                }
+                .into_view(cx)
+            });
+        };
+        if !embeds.is_enabled(provider) {
+            return Some(view! { cx,
+                <div class="text-sm text-gray-500">{format!("{} embeds are turned off", provider.name())}</div>
+            }.into_view(cx));
+        }
+        let player = match embed {
+            Embed::Video(src) => return Some(view! { cx, <VideoPlayer src post_id/> }.into_view(cx)),
+            Embed::Iframe(_) if settings.click_to_load && !loaded() => view! { cx,
+                <button class="bg-neutral-800 hover:bg-neutral-500 rounded px-2" on:click=move |_| set_loaded(true)>
+                    {format!("load {} embed", provider.name())}
+                </button>
+            }.into_view(cx),
+            Embed::Iframe(src) => view! { cx,
+                <iframe lazy="true" sandbox="allow-scripts allow-same-origin allow-popups" allowfullscreen
+                frameborder="0" scrolling="no" class="h-96 w-fit aspect-video" src=src></iframe>
+            }.into_view(cx),
+        };
+        Some(view! { cx,
+            {player}
+            <a class="text-sm text-gray-500 underline" target="_blank" href=link>{format!("via {}", provider.name())}</a>
+        }.into_view(cx))
+    }
+}
+
+/// Turning providers on and off, and where privacy friendly frontends are hosted
+#[component]
+pub fn EmbedSettingsView(cx: Scope) -> impl IntoView {
+    let embeds = use_context::<Embeds>(cx).unwrap();
+    let settings = embeds.0;
+    let youtube_kind = move || match settings.with(|s| s.youtube.clone()) {
+        YoutubeFrontend::NoCookie => "nocookie",
+        YoutubeFrontend::Invidious(_) => "invidious",
+        YoutubeFrontend::Piped(_) => "piped",
+    };
+    let youtube_instance = move || match settings.with(|s| s.youtube.clone()) {
+        YoutubeFrontend::NoCookie => String::new(),
+        YoutubeFrontend::Invidious(i) | YoutubeFrontend::Piped(i) => i,
+    };
+    let set_youtube = move |kind: &str, instance: String| {
+        let frontend = match kind {
+            "invidious" => YoutubeFrontend::Invidious(if instance.is_empty() {
+                INVIDIOUS_HOSTS[0].to_string()
+            } else {
+                instance
+            }),
+            "piped" => YoutubeFrontend::Piped(if instance.is_empty() {
+                PIPED_HOSTS[0].to_string()
+            } else {
+                instance
+            }),
+            _ => YoutubeFrontend::NoCookie,
+        };
+        settings.update(|s| s.youtube = frontend);
+    };
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"Embeds"</div>
+            <label class="flex flex-row gap-2">
+                <input type="checkbox" prop:checked=move || settings.with(|s| s.click_to_load) on:change=move |e| {
+                    let checked = event_target_checked(&e);
+                    settings.update(|s| s.click_to_load = checked);
+                }/>
+                "only load embeds from other sites when clicked"
+            </label>
+            {PROVIDERS
+                .iter()
+                .map(|provider| {
+                    let id = provider.id();
+                    view! { cx,
+                        <label class="flex flex-row gap-2">
+                            <input type="checkbox" prop:checked=move || embeds.is_enabled(*provider) on:change=move |e| {
+                                let enabled = event_target_checked(&e);
+                                settings.update(|s| {
+                                    s.disabled.retain(|d| d != id);
+                                    if !enabled {
+                                        s.disabled.push(id.to_string());
+                                    }
+                                });
+                            }/>
+                            {provider.name()}
+                        </label>
+                    }
+                })
+                .collect::<Vec<_>>()}
+            <div class="text-xl">"YouTube"</div>
+            <div class="flex flex-row gap-2">
+                <select class="bg-neutral-800 rounded" on:change=move |e| set_youtube(&event_target_value(&e), String::new())>
+                    <option value="nocookie" selected=move || youtube_kind() == "nocookie">"youtube-nocookie.com"</option>
+                    <option value="invidious" selected=move || youtube_kind() == "invidious">"Invidious"</option>
+                    <option value="piped" selected=move || youtube_kind() == "piped">"Piped"</option>
+                </select>
+                <input
+                    class="bg-neutral-800 rounded px-1"
+                    placeholder="instance, e.g. yewtu.be"
+                    class:hidden=move || youtube_kind() == "nocookie"
+                    prop:value=youtube_instance
+                    on:change=move |e| set_youtube(youtube_kind(), event_target_value(&e))
+                />
+            </div>
+            <div class="text-xl">"PeerTube instances"</div>
+            <textarea
+                class="bg-neutral-800 rounded px-1 w-fit h-24"
+                placeholder="one per line, e.g. framatube.org"
+                prop:value=move || settings.with(|s| s.peertube_instances.join("\n"))
+                on:change=move |e| {
+                    let instances = event_target_value(&e)
+                        .lines()
+                        .map(|i| i.trim().trim_start_matches("https://").trim_end_matches('/').to_string())
+                        .filter(|i| !i.is_empty())
+                        .collect();
+                    settings.update(|s| s.peertube_instances = instances);
+                }
+            />
+            <div class="text-xl">"Nitter instance"</div>
+            <input
+                class="bg-neutral-800 rounded px-1 w-fit"
+                prop:value=move || settings.with(|s| s.nitter_instance.clone())
+                on:change=move |e| {
+                    let instance = event_target_value(&e);
+                    settings.update(|s| s.nitter_instance = instance);
+                }
+            />
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed(url: &str) -> Option<(&'static str, Embed)> {
+        embed_with(url, &EmbedSettings::default())
+    }
+
+    fn embed_with(url: &str, settings: &EmbedSettings) -> Option<(&'static str, Embed)> {
+        find(&Url::parse(url).unwrap(), settings).map(|(provider, embed)| (provider.id(), embed))
+    }
+
+    fn iframe(id: &'static str, src: &str) -> Option<(&'static str, Embed)> {
+        Some((id, Embed::Iframe(src.to_string())))
+    }
+
+    #[test]
+    fn youtube() {
+        let nocookie = "https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?autoplay=0";
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://m.youtube.com/watch?v=dQw4w9WgXcQ&t=10",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://youtube.com/shorts/dQw4w9WgXcQ",
+            "https://yewtu.be/watch?v=dQw4w9WgXcQ",
+            "https://piped.video/watch?v=dQw4w9WgXcQ",
+        ] {
+            assert_eq!(embed(url), iframe("youtube", nocookie), "{url}");
+        }
+        assert_eq!(embed("https://www.youtube.com/@channel"), None);
+        assert_eq!(embed("https://www.youtube.com/watch"), None);
+    }
+
+    #[test]
+    fn youtube_frontends() {
+        let url = "https://youtu.be/dQw4w9WgXcQ";
+        let invidious = EmbedSettings {
+            youtube: YoutubeFrontend::Invidious("yewtu.be".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            embed_with(url, &invidious),
+            iframe("youtube", "https://yewtu.be/embed/dQw4w9WgXcQ?autoplay=0")
+        );
+        let piped = EmbedSettings {
+            youtube: YoutubeFrontend::Piped("piped.video".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            embed_with(url, &piped),
+            iframe("youtube", "https://piped.video/embed/dQw4w9WgXcQ")
+        );
+    }
+
+    #[test]
+    fn imgur() {
+        let album = iframe("imgur", "https://imgur.com/a/AbC12/embed?pub=true");
+        assert_eq!(embed("https://imgur.com/a/AbC12"), album);
+        assert_eq!(embed("https://imgur.com/gallery/AbC12"), album);
+        assert_eq!(
+            embed("https://i.imgur.com/AbC12.gifv"),
+            Some((
+                "imgur",
+                Embed::Video("https://i.imgur.com/AbC12.mp4".to_string())
+            ))
+        );
+        assert_eq!(embed("https://i.imgur.com/AbC12.png"), None);
+    }
+
+    #[test]
+    fn streamable() {
+        let player = iframe("streamable", "https://streamable.com/e/abc12");
+        assert_eq!(embed("https://streamable.com/abc12"), player);
+        assert_eq!(embed("https://streamable.com/e/abc12"), player);
+    }
+
+    #[test]
+    fn vimeo() {
+        let player = iframe("vimeo", "https://player.vimeo.com/video/123456?dnt=1");
+        assert_eq!(embed("https://vimeo.com/123456"), player);
+        assert_eq!(embed("https://player.vimeo.com/video/123456"), player);
+        assert_eq!(embed("https://vimeo.com/channels"), None);
+    }
+
+    #[test]
+    fn nitter() {
+        let tweet = iframe("twitter", "https://nitter.net/rustlang/status/123/embed");
+        assert_eq!(embed("https://twitter.com/rustlang/status/123"), tweet);
+        assert_eq!(embed("https://x.com/rustlang/status/123?s=20"), tweet);
+        assert_eq!(embed("https://nitter.net/rustlang/status/123"), tweet);
+        assert_eq!(embed("https://twitter.com/rustlang"), None);
+        let other = EmbedSettings {
+            nitter_instance: "nitter.example".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            embed_with("https://twitter.com/rustlang/status/123", &other),
+            iframe(
+                "twitter",
+                "https://nitter.example/rustlang/status/123/embed"
+            )
+        );
+    }
+
+    #[test]
+    fn reddit() {
+        assert_eq!(
+            embed("https://www.reddit.com/gallery/abc12"),
+            iframe("reddit", "https://embed.reddit.com/comments/abc12")
+        );
+        assert_eq!(
+            embed("https://old.reddit.com/r/rust/comments/abc12/some_title/"),
+            iframe("reddit", "https://embed.reddit.com/r/rust/comments/abc12")
+        );
+        assert_eq!(embed("https://www.reddit.com/r/rust"), None);
+    }
+
+    #[test]
+    fn peertube() {
+        let player = iframe(
+            "peertube",
+            "https://framatube.org/videos/embed/kkGMgK9ZtnKfYAgnEtQxbv?peertubeLink=0&warningTitle=0",
+        );
+        assert_eq!(
+            embed("https://framatube.org/w/kkGMgK9ZtnKfYAgnEtQxbv"),
+            player
+        );
+        assert_eq!(
+            embed("https://framatube.org/videos/watch/kkGMgK9ZtnKfYAgnEtQxbv"),
+            player
+        );
+        assert_eq!(embed("https://framatube.org/w/short"), None);
+        // other sites with the same paths aren't peertube
+        assert_eq!(embed("https://example.com/w/kkGMgK9ZtnKfYAgnEtQxbv"), None);
+        assert_eq!(
+            embed("https://example.com/videos/watch/9c9de5e8-0a1e-484a-b099-e80766180a6d"),
+            None
+        );
+        let listed = EmbedSettings {
+            peertube_instances: vec!["example.com".to_string()],
+            ..Default::default()
+        };
+        assert!(embed_with("https://example.com/w/kkGMgK9ZtnKfYAgnEtQxbv", &listed).is_some());
+    }
+
+    #[test]
+    fn direct() {
+        assert_eq!(
+            embed("https://example.com/clip.mp4"),
+            Some((
+                "direct",
+                Embed::Video("https://example.com/clip.mp4".to_string())
+            ))
+        );
+        assert_eq!(embed("https://example.com/picture.png"), None);
+        assert_eq!(embed("https://example.com/article"), None);
+    }
+}
@@ -0,0 +1,317 @@
+use capybara_lemmy_client::post::Post;
+use leptos::*;
+
+use crate::{
+    components::{
+        community::CommunityBadge,
+        markdown::Markdown,
+        person::PersonView,
+        save_button::SaveButton,
+        share::{app_link, instance_link, ShareMenu},
+        show_more::ShowMore,
+        time::RelativeTime,
+        voter::Voter,
+    },
+    nsfw::Nsfw,
+};
+
+use super::{
+    embeds::{Embeds, PostEmbed},
+    lightbox::Lightbox,
+    media::MediaKind,
+    post_preview::{GlobalViewMode, LemmyImage, PostActions, PostData, ViewMode},
+};
+
+#[component]
+pub(super) fn PostVoter(cx: Scope, actions: PostActions) -> impl IntoView {
+    let state = actions.state;
+    view! { cx,
+        <Voter
+            my_vote=Signal::derive(cx, move || state.with(|s| s.my_vote))
+            upvotes=Signal::derive(cx, move || state.with(|s| s.upvotes))
+            downvotes=Signal::derive(cx, move || state.with(|s| s.downvotes))
+            score=Signal::derive(cx, move || state.with(|s| s.score))
+            on_vote=move |vote: Option<i16>| actions.vote(vote)
+        />
+    }
+}
+
+/// Whether the post's media is blurred, the nsfw policy can change while it's shown
+fn nsfw_blur(cx: Scope, data: &PostData) -> Signal<bool> {
+    let nsfw = use_context::<Nsfw>(cx).unwrap();
+    let (post, community) = (data.post.clone(), data.community.clone());
+    Signal::derive(cx, move || nsfw.blurs_post(&post, &community))
+}
+
+/// Who posted it where and when, and the badges
+#[component]
+fn PostMeta(cx: Scope, data: PostData) -> impl IntoView {
+    let PostData {
+        post,
+        creator,
+        community,
+        subscribed,
+        actions,
+        ..
+    } = data;
+    let Post {
+        locked,
+        nsfw,
+        published,
+        updated,
+        featured_community,
+        featured_local,
+        ..
+    } = post;
+    view! { cx,
+        <div class="flex flex-row flex-wrap gap-1">
+            <PersonView person=creator/>
+            "to"
+            <CommunityBadge community subscribed />
+            {locked
+                .then(|| {
+                    view! { cx, <div class="bg-slate-500 px-2 rounded">"locked"</div> }
+                })}
+            {nsfw
+                .then(|| {
+                    view! { cx, <div class="bg-red-600 px-2 rounded">"nsfw"</div> }
+                })}
+            <RelativeTime time=published/>
+            {updated
+                .map(|u| {
+                    view! { cx,
+                        "(updated "
+                        <RelativeTime time=u/>
+                        ")"
+                    }
+                })}
+            {featured_community.then(|| view!{cx, "📌"})}
+            {featured_local.then(|| view!{cx, "📍"})}
+            {move || actions.state.with(|s| s.read).then(|| view!{cx, <div class="px-2 rounded bg-gray-800">"read"</div>})}
+        </div>
+    }
+}
+
+/// Comment count, sharing and saving
+#[component]
+fn PostFooter(cx: Scope, data: PostData) -> impl IntoView {
+    let PostData {
+        post,
+        comments,
+        unread_comments,
+        actions,
+        ..
+    } = data;
+    let post_id = post.id;
+    view! { cx,
+        <div class="flex flex-row gap-2 p-1 leading-none">
+            <a
+                class="text-gray-500 hover:text-gray-400 underline flex flex-row"
+                href=format!("/post/{}", post_id.0)
+            >
+                {comments}
+                " comments "
+                {(comments != unread_comments && unread_comments != 0)
+                    .then(|| {
+                        view! { cx, <div class="text-red-300">"(" {unread_comments} " unread)"</div> }
+                    })}
+            </a>
+            <ShareMenu targets=move || vec![
+                ("original link", post.ap_id.to_string()),
+                ("link on this instance", instance_link(cx, &format!("/post/{}", post_id.0))),
+                ("app link", app_link(&post.ap_id)),
+                ("markdown link", format!("[{}]({})", post.name.replace(['[', ']'], ""), post.ap_id)),
+            ]/>
+            <SaveButton saved=Signal::derive(cx, move || actions.state.with(|s| s.saved)) on_save=move |save: bool| actions.save(save)/>
+        </div>
+    }
+}
+
+/// The full post with its body and embeds, images get bigger in the big images mode
+#[component]
+pub fn ListLayout(cx: Scope, data: PostData) -> impl IntoView {
+    let view_mode = use_context::<GlobalViewMode>(cx).unwrap();
+    let blur = nsfw_blur(cx, &data);
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    let post_id = data.post.id;
+    let embeds = use_context::<Embeds>(cx).unwrap();
+    let has_embed = {
+        let data = data.clone();
+        move || embeds.0.with(|e| data.has_embed(e))
+    };
+    let detected_video = data.media == Some(MediaKind::Video);
+    let Post {
+        name,
+        url,
+        body,
+        embed_title,
+        embed_description,
+        embed_video_url,
+        ..
+    } = data.post.clone();
+    view! { cx,
+        <div class="flex flex-row bg-neutral-900 hover:border-neutral-700 p-1 border-neutral-500 border-b-4">
+            <PostVoter actions=data.actions/>
+            <div class="flex flex-col">
+                <PostMeta data=data.clone()/>
+                <div class="flex flex-row">
+                    <div class="text-lg">{name}</div>
+                </div>
+                <div class="blur hidden"></div>
+                <div class:blur=move || blur() class="hover:blur-none">
+                    {url.as_ref()
+                        .map(|url| {
+                            view! { cx,
+                                <a
+                                    target="_blank"
+                                    class="text-gray-500 hover:text-gray-400 underline"
+                                    href=url.to_string()
+                                >
+                                    {url.to_string()}
+                                </a>
+                            }
+                        })}
+                    {data.image.clone()
+                        .map(|url| {
+                            let big = move || view_mode.0() != ViewMode::Default;
+                            view! { cx,
+                                <div class="w-fit" on:click=move |_| lightbox.open(post_id)>
+                                    <LemmyImage
+                                        src=url
+                                        sizes=Signal::derive(cx, move || if big() { "100vw" } else { "384px" }.to_string())
+                                        width=Signal::derive(cx, move || if big() { 2048 } else { 768 })
+                                        class=Signal::derive(cx, move || {
+                                            if big() {
+                                                "max-h-[calc(100vh-200px)] max-w-full min-h-96 min-w-96 object-scale-down cursor-zoom-in"
+                                            } else {
+                                                "max-h-96 max-w-96 object-scale-down cursor-zoom-in"
+                                            }
+                                            .to_string()
+                                        })
+                                    />
+                                </div>
+                            }
+                        })}
+                    {body
+                        .map(|body| {
+                            view! { cx, <ShowMore><Markdown content=body/></ShowMore> }
+                        })}
+                    <div class="bg-neutral-700 p-1 rounded" class:hidden=move || !has_embed()>
+                        {embed_title
+                            .map(|title| {
+                                view! { cx, <div class="text-md">{title}</div> }
+                            })}
+                        {embed_description
+                            .map(|description| {
+                                view! { cx, <div class="text-sm">{description}</div> }
+                            })}
+                        <PostEmbed url=url embed_video_url post_id detected_video/>
+                    </div>
+                </div>
+                <PostFooter data/>
+            </div>
+        </div>
+    }
+}
+
+/// One line per post, for skimming
+#[component]
+pub fn CompactLayout(cx: Scope, data: PostData) -> impl IntoView {
+    let blur = nsfw_blur(cx, &data);
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    let post_id = data.post.id;
+    let post_href = format!("/post/{}", post_id.0);
+    view! { cx,
+        <div class="flex flex-row gap-2 items-center bg-neutral-900 hover:bg-neutral-800 p-1 border-neutral-700 border-b">
+            <PostVoter actions=data.actions/>
+            {match data.image.clone() {
+                Some(url) => view! { cx,
+                    <div class="shrink-0" on:click=move |_| lightbox.open(post_id)>
+                        <LemmyImage
+                            src=url
+                            sizes="64px"
+                            width=128
+                            class="w-16 h-16 object-cover rounded hover:blur-none cursor-zoom-in"
+                            blur
+                        />
+                    </div>
+                }.into_view(cx),
+                None => view! { cx, <div class="w-16 h-16 shrink-0 rounded bg-neutral-800"></div> }.into_view(cx),
+            }}
+            <div class="flex flex-col min-w-0 text-sm">
+                <a class="text-base truncate hover:underline" href=post_href>{data.post.name.clone()}</a>
+                <PostMeta data=data.clone()/>
+                <PostFooter data/>
+            </div>
+        </div>
+    }
+}
+
+/// A card in a grid, the image on top and the title under it
+#[component]
+pub fn CardLayout(cx: Scope, data: PostData) -> impl IntoView {
+    let blur = nsfw_blur(cx, &data);
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    let post_id = data.post.id;
+    let post_href = format!("/post/{}", post_id.0);
+    view! { cx,
+        <div class="flex flex-col bg-neutral-900 hover:bg-neutral-800 rounded-md m-1 overflow-hidden">
+            {data.image.clone().map(|url| view! { cx,
+                <div on:click=move |_| lightbox.open(post_id)>
+                    <LemmyImage
+                        src=url
+                        sizes="(max-width: 768px) 100vw, 33vw"
+                        width=1024
+                        class="w-full max-h-96 object-cover hover:blur-none cursor-zoom-in"
+                        blur
+                    />
+                </div>
+            })}
+            <div class="flex flex-col gap-1 p-2 text-sm">
+                <a class="text-lg hover:underline" href=post_href>{data.post.name.clone()}</a>
+                <PostMeta data=data.clone()/>
+                <div class="flex flex-row items-center">
+                    <PostVoter actions=data.actions/>
+                    <PostFooter data/>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Just the image, the title and score show on hover
+#[component]
+pub fn GalleryTile(cx: Scope, data: PostData) -> impl IntoView {
+    let blur = nsfw_blur(cx, &data);
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    let state = data.actions.state;
+    let post_id = data.post.id;
+    let name = data.post.name.clone();
+    view! { cx,
+        <div class="relative m-1 rounded overflow-hidden group">
+            {match data.image {
+                Some(url) => view! { cx,
+                    <div on:click=move |_| lightbox.open(post_id)>
+                        <LemmyImage
+                            src=url
+                            sizes="(max-width: 768px) 50vw, 25vw"
+                            width=512
+                            class="w-full object-cover group-hover:blur-none cursor-zoom-in"
+                            blur
+                        />
+                    </div>
+                }.into_view(cx),
+                // posts without an image still get a tile so the feed stays complete
+                None => view! { cx,
+                    <a class="block h-32 p-2 bg-neutral-800 overflow-hidden" href=format!("/post/{}", post_id.0)>
+                        {name.clone()}
+                    </a>
+                }.into_view(cx),
+            }}
+            <div class="absolute bottom-0 inset-x-0 flex flex-row gap-2 p-1 text-sm bg-black/60 opacity-0 group-hover:opacity-100">
+                <a class="grow truncate hover:underline" href=format!("/post/{}", post_id.0)>{name}</a>
+                <div>{move || state.with(|s| s.score)}</div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,276 @@
+use capybara_lemmy_client::post::{Post, PostId, PostView};
+use leptos::*;
+use leptos_use::use_event_listener;
+
+use crate::components::image_proxy::ImageProxy;
+
+use super::{
+    layouts::PostVoter,
+    media::{MediaKind, MediaTypes},
+    post_preview::{preview_image, PostActions},
+    video::load_source,
+};
+
+const MAX_ZOOM: f64 = 8.0;
+/// How far a pointer has to travel to count as a swipe, in pixels
+const SWIPE_DISTANCE: f64 = 60.0;
+
+/// The image or video of a post as it's shown in the lightbox
+#[derive(Clone, PartialEq)]
+pub struct Media {
+    pub src: String,
+    pub video: bool,
+}
+
+/// The full size media of a post, if it has any
+pub fn post_media(post: &Post, media_types: &MediaTypes) -> Option<Media> {
+    let url = post.url.as_ref().map(|u| u.to_string()).unwrap_or_default();
+    let kind = media_types.cached(&url);
+    if kind == Some(MediaKind::Video) {
+        return Some(Media {
+            src: url,
+            video: true,
+        });
+    }
+    if kind == Some(MediaKind::Image) {
+        return Some(Media {
+            src: url,
+            video: false,
+        });
+    }
+    // embeds are often a player page rather than the video itself, ask when the url doesn't say
+    if let Some(video) = post
+        .embed_video_url
+        .as_ref()
+        .map(|u| u.to_string())
+        .filter(|u| media_types.kind(u) == Some(MediaKind::Video))
+    {
+        return Some(Media {
+            src: video,
+            video: true,
+        });
+    }
+    preview_image(post).map(|src| Media { src, video: false })
+}
+
+/// Shows the media of a post full screen, flipping through the rest of the feed it's in
+#[derive(Clone, Copy)]
+pub struct Lightbox {
+    /// posts whose media can be navigated between
+    feed: RwSignal<Option<RwSignal<Vec<PostView>>>>,
+    open: RwSignal<Option<PostId>>,
+    media_types: MediaTypes,
+}
+
+impl Lightbox {
+    pub fn new(cx: Scope, media_types: MediaTypes) -> Self {
+        Self {
+            feed: create_rw_signal(cx, None),
+            open: create_rw_signal(cx, None),
+            media_types,
+        }
+    }
+
+    /// Makes `posts` the feed to navigate through, until `cx` is cleaned up
+    pub fn set_feed(&self, cx: Scope, posts: RwSignal<Vec<PostView>>) {
+        let lightbox = *self;
+        lightbox.feed.set(Some(posts));
+        on_cleanup(cx, move || {
+            if lightbox.feed.get_untracked() == Some(posts) {
+                lightbox.feed.set(None);
+                lightbox.open.set(None);
+            }
+        });
+    }
+
+    pub fn open(&self, post_id: PostId) {
+        self.open.set(Some(post_id));
+    }
+
+    pub fn close(&self) {
+        self.open.set(None);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open.with(|o| o.is_some())
+    }
+
+    fn current(&self) -> Option<PostView> {
+        let post_id = self.open.get()?;
+        let posts = self.feed.get()?;
+        posts.with(|p| p.iter().find(|p| p.post.id == post_id).cloned())
+    }
+
+    /// Moves to the next (or previous) post in the feed that has media
+    fn step(&self, forward: bool) {
+        let (Some(post_id), Some(posts)) = (self.open.get_untracked(), self.feed.get_untracked())
+        else {
+            return;
+        };
+        let next = posts.with_untracked(|posts| {
+            let index = posts.iter().position(|p| p.post.id == post_id)?;
+            let has_media = |p: &&PostView| post_media(&p.post, &self.media_types).is_some();
+            if forward {
+                posts[index + 1..].iter().find(has_media).map(|p| p.post.id)
+            } else {
+                posts[..index]
+                    .iter()
+                    .rev()
+                    .find(has_media)
+                    .map(|p| p.post.id)
+            }
+        });
+        if let Some(next) = next {
+            self.open.set(Some(next));
+        }
+    }
+}
+
+/// Its own component so the streaming player is torn down when another post is opened
+#[component]
+fn LightboxVideo(cx: Scope, src: String, style: Signal<String>) -> impl IntoView {
+    let video = create_node_ref(cx);
+    load_source(cx, video, src);
+    view! { cx,
+        <video class="relative max-h-full max-w-full" controls autoplay node_ref=video style=style></video>
+    }
+}
+
+/// The lightbox itself, rendered once by the app
+#[component]
+pub fn LightboxView(cx: Scope) -> impl IntoView {
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    // only changes when another post is opened, not when the feed loads more
+    let current = create_memo(cx, move |_| lightbox.current());
+    let zoom = create_rw_signal(cx, 1.0);
+    let offset = create_rw_signal(cx, (0.0, 0.0));
+    let reset = move || {
+        zoom.set(1.0);
+        offset.set((0.0, 0.0));
+    };
+    let zoom_by = move |factor: f64| {
+        zoom.update(|z| *z = (*z * factor).clamp(1.0, MAX_ZOOM));
+        if zoom.get_untracked() == 1.0 {
+            offset.set((0.0, 0.0));
+        }
+    };
+    create_effect(cx, move |_| {
+        current.with(|_| ());
+        reset();
+    });
+    let _ = use_event_listener(cx, window(), ev::keydown, move |e| {
+        if !lightbox.open.with_untracked(|o| o.is_some()) {
+            return;
+        }
+        match e.key().as_str() {
+            "Escape" => lightbox.close(),
+            "ArrowLeft" => lightbox.step(false),
+            "ArrowRight" => lightbox.step(true),
+            "+" | "=" => zoom_by(1.25),
+            "-" => zoom_by(0.8),
+            "0" => reset(),
+            _ => return,
+        }
+        e.prevent_default();
+    });
+    // where a drag started, and the offset at that point
+    let drag = store_value(cx, None::<((f64, f64), (f64, f64))>);
+    // a swipe or pan ending on the backdrop shouldn't count as a click on it
+    let dragged = store_value(cx, false);
+    let on_pointerdown = move |e: ev::PointerEvent| {
+        let start = (e.client_x() as f64, e.client_y() as f64);
+        drag.set_value(Some((start, offset.get_untracked())));
+    };
+    let on_pointermove = move |e: ev::PointerEvent| {
+        let Some(((x, y), (ox, oy))) = drag.get_value() else {
+            return;
+        };
+        if zoom.get_untracked() > 1.0 {
+            offset.set((ox + e.client_x() as f64 - x, oy + e.client_y() as f64 - y));
+        }
+    };
+    let on_pointerup = move |e: ev::PointerEvent| {
+        let Some(((x, y), _)) = drag.get_value() else {
+            return;
+        };
+        drag.set_value(None);
+        let (dx, dy) = (e.client_x() as f64 - x, e.client_y() as f64 - y);
+        dragged.set_value(dx.abs() + dy.abs() > 10.0);
+        // zoomed in the pointer pans, otherwise it swipes
+        if zoom.get_untracked() > 1.0 {
+            return;
+        }
+        if dx.abs() > SWIPE_DISTANCE && dx.abs() > dy.abs() {
+            lightbox.step(dx < 0.0);
+        } else if dy > SWIPE_DISTANCE * 2.0 {
+            lightbox.close();
+        }
+    };
+    let proxy = use_context::<ImageProxy>(cx).unwrap();
+    view! { cx,
+        {move || {
+            let post = current()?;
+            let media = post_media(&post.post, &lightbox.media_types)?;
+            let actions = PostActions::new(cx, &post);
+            let post_href = format!("/post/{}", post.post.id.0);
+            let transform = move || {
+                let (x, y) = offset();
+                format!("transform: translate({x}px, {y}px) scale({})", zoom())
+            };
+            Some(view! { cx,
+                <div
+                    class="fixed inset-0 z-[100] bg-black/90 flex items-center justify-center overflow-hidden touch-none select-none"
+                    on:pointerdown=on_pointerdown
+                    on:pointermove=on_pointermove
+                    on:pointerup=on_pointerup
+                    on:pointercancel=move |_| drag.set_value(None)
+                    on:wheel=move |e: ev::WheelEvent| {
+                        e.prevent_default();
+                        zoom_by(if e.delta_y() < 0.0 { 1.1 } else { 1.0 / 1.1 });
+                    }
+                >
+                    <div class="absolute inset-0" on:click=move |_| {
+                        if !dragged.get_value() {
+                            lightbox.close();
+                        }
+                    }></div>
+                    {if media.video {
+                        view! { cx, <LightboxVideo src=media.src style=Signal::derive(cx, transform)/> }.into_view(cx)
+                    } else {
+                        let src = media.src;
+                        view! { cx,
+                            <img
+                                class="relative max-h-full max-w-full object-contain"
+                                draggable="false"
+                                src=move || proxy.src(&src, None)
+                                style=transform
+                                on:dblclick=move |_| {
+                                    if zoom.get_untracked() > 1.0 { reset() } else { zoom_by(2.5) }
+                                }
+                            />
+                        }.into_view(cx)
+                    }}
+                    <div class="absolute top-0 inset-x-0 flex flex-row items-start gap-2 p-2 bg-black/60">
+                        <PostVoter actions/>
+                        <a class="grow text-lg hover:underline" href=post_href on:click=move |_| lightbox.close()>
+                            {post.post.name}
+                        </a>
+                        <button class="px-2 text-2xl hover:text-gray-400" on:click=move |_| lightbox.close()>"×"</button>
+                    </div>
+                    <button
+                        class="absolute left-2 top-1/2 px-2 text-4xl bg-black/40 rounded hover:text-gray-400"
+                        on:click=move |_| lightbox.step(false)
+                    >
+                        "‹"
+                    </button>
+                    <button
+                        class="absolute right-2 top-1/2 px-2 text-4xl bg-black/40 rounded hover:text-gray-400"
+                        on:click=move |_| lightbox.step(true)
+                    >
+                        "›"
+                    </button>
+                </div>
+            })
+        }}
+    }
+}
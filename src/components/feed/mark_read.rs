@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use capybara_lemmy_client::post::{MarkPostAsRead, PostId};
+use leptos::*;
+
+use crate::{
+    app::CurrentUser,
+    mutation::{ItemStore, PostState},
+};
+
+/// Posts are sent once this many are waiting, or after `BATCH_DELAY`. Lemmy 0.18 only marks one
+/// post per request, so a batch is a window of requests sent together, not a single request.
+const BATCH_SIZE: usize = 10;
+const BATCH_DELAY: Duration = Duration::from_secs(3);
+
+/// Marks posts as read as they're scrolled past, when the user opted in
+#[derive(Clone, Copy)]
+pub struct MarkRead {
+    pub enabled: RwSignal<bool>,
+    /// the app's scope, rows of the feed can be gone by the time a batch is sent
+    cx: Scope,
+    pending: StoredValue<Vec<(PostId, PostState)>>,
+    flush_scheduled: StoredValue<bool>,
+    /// posts in the most recently sent batch, so it can be undone
+    last_batch: RwSignal<Vec<PostId>>,
+}
+
+impl MarkRead {
+    pub fn new(cx: Scope, enabled: bool) -> Self {
+        Self {
+            enabled: create_rw_signal(cx, enabled),
+            cx,
+            pending: store_value(cx, vec![]),
+            flush_scheduled: store_value(cx, false),
+            last_batch: create_rw_signal(cx, vec![]),
+        }
+    }
+
+    /// Called when a post has scrolled out of view past the top of the screen
+    pub fn post_seen(&self, post_id: PostId, state: PostState) {
+        let logged_in = use_context::<CurrentUser>(self.cx)
+            .is_some_and(|user| user.with_untracked(|u| u.is_some()));
+        if !logged_in || !self.enabled.get_untracked() || state.read {
+            return;
+        }
+        let mut queued = false;
+        self.pending.update_value(|pending| {
+            if !pending.iter().any(|(id, _)| *id == post_id) {
+                pending.push((post_id, state));
+                queued = true;
+            }
+        });
+        if !queued {
+            return;
+        }
+        if self.pending.with_value(|p| p.len()) >= BATCH_SIZE {
+            self.flush();
+        } else if !self.flush_scheduled.get_value() {
+            self.flush_scheduled.set_value(true);
+            let mark_read = *self;
+            set_timeout(move || mark_read.flush(), BATCH_DELAY);
+        }
+    }
+
+    /// Sends a `MarkPostAsRead` for each waiting post
+    fn flush(&self) {
+        self.flush_scheduled.set_value(false);
+        let batch = self.pending.get_value();
+        self.pending.set_value(vec![]);
+        if batch.is_empty() {
+            return;
+        }
+        let store = use_context::<ItemStore>(self.cx).unwrap();
+        for (post_id, fetched) in &batch {
+            let current = store.current(*post_id).unwrap_or_else(|| fetched.clone());
+            let request = MarkPostAsRead {
+                post_id: *post_id,
+                read: true,
+                ..Default::default()
+            };
+            store.mutate(self.cx, *post_id, current, |s| s.read = true, request);
+        }
+        self.last_batch
+            .set(batch.into_iter().map(|(post_id, _)| post_id).collect());
+    }
+
+    /// Marks the last batch as unread again
+    pub fn undo(&self) {
+        let store = use_context::<ItemStore>(self.cx).unwrap();
+        for post_id in self.last_batch.get_untracked() {
+            let Some(current) = store.current::<PostState>(post_id) else {
+                continue;
+            };
+            let request = MarkPostAsRead {
+                post_id,
+                read: false,
+                ..Default::default()
+            };
+            store.mutate(self.cx, post_id, current, |s| s.read = false, request);
+        }
+        self.last_batch.set(vec![]);
+    }
+}
+
+/// Offers to undo the last batch of posts that were marked read
+#[component]
+pub fn UndoMarkRead(cx: Scope) -> impl IntoView {
+    let mark_read = use_context::<MarkRead>(cx).unwrap();
+    let count = move || mark_read.last_batch.with(|b| b.len());
+    view! { cx,
+        <div
+            class="flex flex-row gap-2 fixed bottom-20 right-20 z-50 bg-neutral-700 rounded-md px-2"
+            class:hidden=move || count() == 0
+        >
+            {move || format!("marked {} read", count())}
+            <button class="underline hover:text-gray-300" on:click=move |_| mark_read.undo()>
+                "undo"
+            </button>
+            <button class="hover:text-gray-300" on:click=move |_| mark_read.last_batch.set(vec![])>
+                "x"
+            </button>
+        </div>
+    }
+}
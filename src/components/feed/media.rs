@@ -0,0 +1,193 @@
+use std::collections::{HashMap, HashSet};
+
+use capybara_lemmy_client::CapyClient;
+use leptos::*;
+use url::Url;
+
+/// What a link points at
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Other,
+}
+
+impl MediaKind {
+    pub fn from_content_type(content_type: &str) -> Self {
+        let mime = content_type.split(';').next().unwrap_or_default().trim();
+        if mime.starts_with("image/") {
+            MediaKind::Image
+        } else if mime.starts_with("video/") {
+            MediaKind::Video
+        } else {
+            MediaKind::Other
+        }
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "png" | "jpg" | "jpeg" | "webp" | "gif" | "avif" | "bmp" | "svg" => {
+                Some(MediaKind::Image)
+            }
+            // m3u8 and mpd are stream manifests, the player handles those
+            "mp4" | "webm" | "mov" | "m4v" | "ogv" | "m3u8" | "mpd" => Some(MediaKind::Video),
+            "html" | "htm" | "php" | "asp" | "aspx" | "pdf" => Some(MediaKind::Other),
+            _ => None,
+        }
+    }
+}
+
+/// Works out what `url` is from the url alone, None if it doesn't say
+pub fn guess(url: &str) -> Option<MediaKind> {
+    let url = Url::parse(url).ok()?;
+    let path = url.path().to_lowercase();
+    if path.is_empty() || path == "/" {
+        return Some(MediaKind::Other);
+    }
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension)
+        .filter(|extension| !extension.contains('/'));
+    if let Some(kind) = extension.and_then(MediaKind::from_extension) {
+        return Some(kind);
+    }
+    // pict-rs serves every upload from here, videos are the ones with an extension saying so
+    if path.starts_with("/pictrs/image/") {
+        return Some(MediaKind::Image);
+    }
+    // CDNs that pick the format from the query, e.g. ?format=webp
+    url.query_pairs()
+        .find(|(key, _)| key == "format")
+        .and_then(|(_, format)| MediaKind::from_extension(&format.to_lowercase()))
+}
+
+/// What links point at, asking the server through the backend when the url doesn't say
+#[derive(Clone, Copy)]
+pub struct MediaTypes {
+    cx: Scope,
+    known: RwSignal<HashMap<String, MediaKind>>,
+    /// urls with a request on the way
+    pending: StoredValue<HashSet<String>>,
+}
+
+impl MediaTypes {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            cx,
+            known: create_rw_signal(cx, HashMap::new()),
+            pending: store_value(cx, HashSet::new()),
+        }
+    }
+
+    /// What `url` is if that's known already, without asking the server
+    pub fn cached(&self, url: &str) -> Option<MediaKind> {
+        guess(url).or_else(|| self.known.with(|k| k.get(url).copied()))
+    }
+
+    /// What `url` is, None until the server has answered
+    pub fn kind(&self, url: &str) -> Option<MediaKind> {
+        let kind = self.cached(url);
+        if kind.is_none() {
+            self.detect(url.to_string());
+        }
+        kind
+    }
+
+    fn detect(&self, url: String) {
+        let mut new = false;
+        self.pending.update_value(|p| new = p.insert(url.clone()));
+        if !new {
+            return;
+        }
+        let media_types = *self;
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(media_types.cx).unwrap();
+            // failures count as not media, so a broken link isn't asked about again
+            let kind = match client.content_type(&url).await {
+                Ok(Some(content_type)) => MediaKind::from_content_type(&content_type),
+                _ => MediaKind::Other,
+            };
+            media_types.pending.update_value(|p| {
+                p.remove(&url);
+            });
+            media_types.known.update(|k| {
+                k.insert(url, kind);
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guesses_from_extensions() {
+        assert_eq!(guess("https://i.imgur.com/abc.JPG"), Some(MediaKind::Image));
+        assert_eq!(
+            guess("https://example.com/a/b.webp?size=large"),
+            Some(MediaKind::Image)
+        );
+        assert_eq!(
+            guess("https://example.com/clip.mp4"),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(
+            guess("https://example.com/live/index.m3u8"),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(
+            guess("https://example.com/stream.mpd"),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(
+            guess("https://example.com/index.php"),
+            Some(MediaKind::Other)
+        );
+        assert_eq!(
+            guess("https://example.com/paper.pdf"),
+            Some(MediaKind::Other)
+        );
+    }
+
+    #[test]
+    fn guesses_from_the_rest_of_the_url() {
+        assert_eq!(guess("https://example.com"), Some(MediaKind::Other));
+        assert_eq!(guess("https://example.com/"), Some(MediaKind::Other));
+        assert_eq!(
+            guess("https://lemmy.ml/pictrs/image/0b5cd6f3-1c8e-4a5c-9d7d-6d2a2b1f3c4e"),
+            Some(MediaKind::Image)
+        );
+        assert_eq!(
+            guess("https://lemmy.ml/pictrs/image/0b5cd6f3.mp4"),
+            Some(MediaKind::Video)
+        );
+        assert_eq!(
+            guess("https://cdn.example.com/photo?format=webp&w=640"),
+            Some(MediaKind::Image)
+        );
+    }
+
+    #[test]
+    fn unknown_urls_need_asking() {
+        assert_eq!(guess("https://example.com/some/article"), None);
+        // a dot in a directory isn't an extension
+        assert_eq!(guess("https://example.com/v1.2/download"), None);
+        assert_eq!(guess("https://example.com/file.xyz"), None);
+        assert_eq!(guess("not a url"), None);
+    }
+
+    #[test]
+    fn reads_content_types() {
+        assert_eq!(MediaKind::from_content_type("image/png"), MediaKind::Image);
+        assert_eq!(
+            MediaKind::from_content_type("video/mp4; codecs=avc1"),
+            MediaKind::Video
+        );
+        assert_eq!(
+            MediaKind::from_content_type("text/html; charset=utf-8"),
+            MediaKind::Other
+        );
+        assert_eq!(MediaKind::from_content_type(""), MediaKind::Other);
+    }
+}
@@ -1,2 +1,9 @@
+pub mod cache;
+pub mod embeds;
+pub mod layouts;
+pub mod lightbox;
+pub mod mark_read;
+pub mod media;
 pub mod post_preview;
+pub mod video;
 pub mod virtual_scroll;
@@ -1,110 +1,187 @@
-use std::ops::Deref;
-
 use crate::{
-    app::{CurrentUser, HideRead},
-    components::{
-        community::CommunityBadge, markdown::Markdown, person::PersonView, show_more::ShowMore,
-        time::RelativeTime, voter::Voter,
-    },
+    account::Account,
+    app::HideRead,
+    components::{image_proxy::ImageProxy, offline::Offline},
+    mutation::{ItemStore, PostState},
 };
-use capybara_lemmy_client::{
-    post::{CreatePostLike, Post, PostAggregates, PostView},
-    CapyClient,
+
+use super::{
+    embeds::{self, EmbedSettings, Embeds},
+    layouts::{CardLayout, CompactLayout, GalleryTile, ListLayout},
+    mark_read::MarkRead,
+    media::{self, MediaKind, MediaTypes},
 };
-use leptos::{html::Video, *};
-use leptos_icons::*;
-use leptos_use::{
-    use_intersection_observer, use_intersection_observer_with_options,
-    UseIntersectionObserverOptions,
+use capybara_lemmy_client::{
+    community::{Community, SubscribedType},
+    person::Person,
+    post::{CreatePostLike, Post, PostId, PostView, SavePost},
 };
-use log::info;
-use serde::Serialize;
-use wasm_bindgen::JsCast;
-use web_sys::HtmlMediaElement;
-
-fn is_magic_embed(url: &str) -> bool {
-    url.starts_with("https://redgifs.com/watch") || url.starts_with("https://www.redgifs.com/watch")
-}
-
-/// Tries to get an embed from known websites
-#[component]
-fn MagicEmbed(cx: Scope, url: String) -> impl IntoView {
-    if is_magic_embed(&url) {
-        let video_id = url.split("/").last();
-        video_id.map(|video_id| { view!{cx, <iframe src=format!("https://www.redgifs.com/ifr/{video_id}") frameborder="0" scrolling="no" allowfullscreen class="object-scale-down h-96 aspect-video"></iframe><p><a href=format!("https://www.redgifs.com/watch/{video_id}")>"via RedGIFs"</a></p>}}).into_view(cx)
-    } else {
-        {}.into_view(cx)
-    };
-}
+use leptos::*;
+use leptos_use::use_intersection_observer;
+use serde::{Deserialize, Serialize};
+use url::Url;
 
 fn is_image(url: &str) -> bool {
-    url.ends_with(".png")
-        || url.ends_with(".webp")
-        || url.ends_with(".jpeg")
-        || url.ends_with(".jpg")
-        || url.ends_with(".gif")
+    media::guess(url) == Some(MediaKind::Image)
 }
 
-fn is_video(url: &str) -> bool {
-    url.ends_with(".mp4") || url.ends_with(".webm")
+/// The image shown in the preview, the thumbnail or the link itself if it's an image
+pub fn preview_image(post: &Post) -> Option<String> {
+    match (&post.thumbnail_url, &post.url) {
+        (Some(thumbnail), _) => Some(thumbnail.to_string()),
+        (None, Some(url)) if is_image(url.as_str()) => Some(url.to_string()),
+        _ => None,
+    }
 }
 
+/// Widths pict-rs is asked for, the browser picks one with `srcset`
+const THUMBNAIL_WIDTHS: [u32; 5] = [128, 256, 512, 1024, 2048];
+
 #[derive(Serialize)]
 enum ImageType {
     #[serde(rename = "webp")]
     Webp,
-    #[serde(rename = "jpeg")]
-    Jpeg,
-    #[serde(rename = "png")]
-    Png,
 }
 
+/// pict-rs's query parameters for a resized copy of an image
 #[derive(Serialize)]
 struct ImageDetails {
     format: ImageType,
     thumbnail: u32,
 }
 
-#[component]
-fn LemmyImage(cx: Scope, thumbnail: Option<String>, link: Option<String>) -> impl IntoView {}
+impl ImageDetails {
+    fn apply(&self, url: &Url) -> String {
+        let mut url = url.clone();
+        let Ok(serde_json::Value::Object(params)) = serde_json::to_value(self) else {
+            return url.to_string();
+        };
+        let kept: Vec<(String, String)> = url
+            .query_pairs()
+            .filter(|(key, _)| !params.contains_key(key.as_ref()))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        let mut query = url.query_pairs_mut();
+        query.clear().extend_pairs(kept);
+        for (key, value) in &params {
+            match value {
+                serde_json::Value::String(value) => query.append_pair(key, value),
+                value => query.append_pair(key, &value.to_string()),
+            };
+        }
+        drop(query);
+        url.to_string()
+    }
+}
 
+/// A webp copy of `url` pict-rs has shrunk to `width`
+fn pictrs_thumbnail(url: &Url, width: u32) -> String {
+    let details = ImageDetails {
+        format: ImageType::Webp,
+        thumbnail: width,
+    };
+    details.apply(url)
+}
+
+fn pictrs_url(src: &str) -> Option<Url> {
+    Url::parse(src)
+        .ok()
+        .filter(|url| url.path().starts_with("/pictrs/image/"))
+}
+
+/// `srcset` of webp thumbnails for images hosted by pict-rs, None for other hosts
+fn pictrs_srcset(src: &str) -> Option<String> {
+    let url = pictrs_url(src)?;
+    let srcset = THUMBNAIL_WIDTHS
+        .iter()
+        .map(|&width| format!("{} {width}w", pictrs_thumbnail(&url, width)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(srcset)
+}
+
+/// An image that loads a thumbnail sized for how it's shown when it's hosted by pict-rs, and
+/// the original otherwise. Through the image proxy, `width` is the size it's fetched at
 #[component]
-fn VideoPlayer(cx: Scope, src: String) -> impl IntoView {
-    let video_player = create_node_ref(cx);
-    let (playback_enabled, set_playback_enabled) = create_signal(cx, false);
-    use_intersection_observer_with_options(
-        cx,
-        video_player,
-        move |entry, _| {
-            set_playback_enabled(entry[0].is_intersecting());
-        },
-        UseIntersectionObserverOptions::default().thresholds(vec![0.8]),
-    );
-    let playback_enabled = create_memo(cx, move |_| playback_enabled());
-    create_effect(cx, move |_| {
-        let player = video_player();
-        let enabled = playback_enabled();
-        if let Some(player) = player.and_then(|p: HtmlElement<Video>| {
-            let p = p.into_any();
-            let cast = p.deref().clone().dyn_into::<HtmlMediaElement>().ok();
-            cast
-        }) {
-            if enabled {
-                let _ = player.play();
-            } else {
-                let _ = player.pause();
-            }
+pub(super) fn LemmyImage(
+    cx: Scope,
+    src: String,
+    /// how wide the image is shown, as in the `sizes` attribute
+    #[prop(into)]
+    sizes: MaybeSignal<String>,
+    /// the widest the image is shown at in pixels, for the image proxy
+    #[prop(into)]
+    width: MaybeSignal<u32>,
+    #[prop(into, optional)] class: MaybeSignal<String>,
+    #[prop(into, optional)] blur: MaybeSignal<bool>,
+) -> impl IntoView {
+    let offline = use_context::<Offline>(cx).unwrap();
+    let proxy = use_context::<ImageProxy>(cx).unwrap();
+    let srcset = pictrs_srcset(&src);
+    let pictrs = pictrs_url(&src);
+    let image_src = move || {
+        if !proxy.enabled.get() {
+            return Some(offline.image_src(&src));
         }
-    });
-    let global_view_mode = use_context::<GlobalViewMode>(cx).unwrap();
-
-    view! {cx, <video controls node_ref=video_player class=move || match global_view_mode.0()  { ViewMode::BigImage => "min-h-96 min-w-96 max-h-[calc(100vh-200px)] max-w-full aspect-video", ViewMode::Default => "h-96 w-fit aspect-video" } src=src />}
+        // pict-rs can shrink its images itself, the backend shrinks everyone else's
+        match &pictrs {
+            Some(url) => proxy.src(&pictrs_thumbnail(url, width.get()), None),
+            None => proxy.src(&src, Some(width.get())),
+        }
+    };
+    view! { cx,
+        <img
+            loading="lazy"
+            class=move || class.get()
+            class:blur=move || blur.get()
+            // downloaded images are stored by their original url
+            srcset=move || (!offline.is_offline() && !proxy.enabled.get()).then(|| srcset.clone()).flatten()
+            sizes=move || sizes.get()
+            src=image_src
+        />
+    }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default, Serialize, Deserialize)]
 pub enum ViewMode {
+    #[default]
     Default,
     BigImage,
+    /// one line per post, title and a small thumbnail
+    Compact,
+    /// a grid of cards
+    Card,
+    /// just the images, for image communities
+    Gallery,
+}
+
+impl ViewMode {
+    pub const ALL: [ViewMode; 5] = [
+        ViewMode::Default,
+        ViewMode::BigImage,
+        ViewMode::Compact,
+        ViewMode::Card,
+        ViewMode::Gallery,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ViewMode::Default => "default",
+            ViewMode::BigImage => "big images",
+            ViewMode::Compact => "compact",
+            ViewMode::Card => "cards",
+            ViewMode::Gallery => "gallery",
+        }
+    }
+
+    /// How many columns the feed is laid out in
+    pub fn columns(&self) -> usize {
+        match self {
+            ViewMode::Card => 3,
+            ViewMode::Gallery => 4,
+            _ => 1,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -116,237 +193,156 @@ impl AsRef<RwSignal<ViewMode>> for GlobalViewMode {
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct GlobalBlurState(pub RwSignal<bool>);
+/// Voting and saving for a post, shared by all the layouts
+#[derive(Clone, Copy)]
+pub struct PostActions {
+    cx: Scope,
+    store: ItemStore,
+    post_id: PostId,
+    pub state: Memo<PostState>,
+}
 
-impl AsRef<RwSignal<bool>> for GlobalBlurState {
-    fn as_ref(&self) -> &RwSignal<bool> {
-        &self.0
+impl PostActions {
+    pub fn new(cx: Scope, post: &PostView) -> Self {
+        let store = use_context::<ItemStore>(cx).unwrap();
+        let post_id = post.post.id;
+        Self {
+            cx,
+            store,
+            post_id,
+            state: store.state(cx, post_id, PostState::from(post)),
+        }
+    }
+
+    pub fn vote(&self, vote: Option<i16>) {
+        let like = CreatePostLike {
+            post_id: self.post_id,
+            score: vote.unwrap_or_default(),
+            ..Default::default()
+        };
+        let current = self.state.get_untracked();
+        self.store
+            .mutate(self.cx, self.post_id, current, |s| s.vote(vote), like);
+    }
+
+    pub fn save(&self, save: bool) {
+        let request = SavePost {
+            post_id: self.post_id,
+            save,
+            ..Default::default()
+        };
+        let current = self.state.get_untracked();
+        self.store
+            .mutate(self.cx, self.post_id, current, |s| s.saved = save, request);
+    }
+}
+
+/// What the layouts render a post from
+#[derive(Clone)]
+pub struct PostData {
+    pub post: Post,
+    pub creator: Person,
+    pub community: Community,
+    pub subscribed: RwSignal<SubscribedType>,
+    pub comments: i64,
+    pub unread_comments: i64,
+    /// the thumbnail, or the link itself if it's an image
+    pub image: Option<String>,
+    /// what the link turned out to be, for links that don't say
+    pub media: Option<MediaKind>,
+    pub actions: PostActions,
+}
+
+impl PostData {
+    /// Whether there's anything to show in the embed box, providers turned off in `settings` don't count
+    pub fn has_embed(&self, settings: &EmbedSettings) -> bool {
+        self.post.embed_title.is_some()
+            || self.post.embed_description.is_some()
+            || self.post.embed_video_url.is_some()
+            || self.media == Some(MediaKind::Video)
+            || self
+                .post
+                .url
+                .as_ref()
+                .and_then(|url| embeds::find(url, settings))
+                .is_some_and(|(provider, _)| settings.is_enabled(provider))
     }
 }
 
 #[component]
-pub fn PostPreview(cx: Scope, post: PostView) -> impl IntoView {
+pub fn PostPreview(
+    cx: Scope,
+    post: PostView,
+    /// shown on its own page, always uses the list layout
+    #[prop(optional)]
+    full: bool,
+) -> impl IntoView {
     let view_mode = use_context::<GlobalViewMode>(cx).unwrap();
-    let global_blur = use_context::<GlobalBlurState>(cx).unwrap();
-    let PostView {
-        post,
-        creator,
-        community,
-        creator_banned_from_community,
-        counts,
-        subscribed,
-        saved,
-        read,
-        creator_blocked,
-        my_vote,
-        unread_comments,
-    } = post;
-    let PostAggregates {
-        post_id,
-        comments,
-        score,
-        upvotes,
-        downvotes,
-        newest_comment_time_necro,
-        newest_comment_time,
-        featured_community,
-        featured_local,
-        hot_rank,
-        hot_rank_active,
-        ..
-    } = counts;
-    let Post {
-        id,
-        name,
-        url,
-        body,
-        creator_id,
-        community_id,
-        removed,
-        locked,
-        published,
-        updated,
-        deleted,
-        nsfw,
-        embed_title,
-        embed_description,
-        thumbnail_url,
-        ap_id,
-        local,
-        embed_video_url,
-        language_id,
-        featured_community,
-        featured_local,
-    } = post;
-    let url_str = url.as_ref().map(|s| s.to_string()).unwrap_or_default();
-    let has_embed = embed_title.is_some()
-        || embed_description.is_some()
-        || embed_video_url.is_some()
-        || is_video(&url_str)
-        || is_magic_embed(&url_str);
-    let subscribed = create_rw_signal(cx, subscribed);
-    // get the score without our vote so we can immediately update the signals locally
-    let my_vote = create_rw_signal(cx, my_vote);
-    let user = use_context::<CurrentUser>(cx).unwrap();
-    create_effect(cx, move |prev| {
-        let vote = my_vote();
-        let user = user();
-        if let Some((Some(prev_user), prev)) = prev {
-            if Some(prev_user) == user && vote != prev {
-                spawn_local(async move {
-                    let client = use_context::<CapyClient>(cx).unwrap();
-                    let like = CreatePostLike {
-                        post_id,
-                        score: vote.unwrap_or_default(),
-                        ..Default::default()
-                    };
-                    // TODO: Actually update the post somehow?
-                    let _ = client.execute(like).await;
-                    info!("liked");
-                });
-            }
-        }
-        (user, vote)
-    });
-    let thumbnail_url = match thumbnail_url {
-        Some(t) => Some(t),
-        None => match &url {
-            Some(url) => {
-                let url_str = url.to_string();
-                let is_image = is_image(&url_str);
-                info!("attempting to detect URL type {url_str} {is_image}");
-                if is_image {
-                    Some(url.clone())
-                } else {
-                    None
-                }
-            }
-            None => None,
-        },
+    let post_id = post.post.id;
+    let read = post.read;
+    let actions = PostActions::new(cx, &post);
+    let data = PostData {
+        image: preview_image(&post.post),
+        post: post.post,
+        creator: post.creator,
+        community: post.community,
+        subscribed: create_rw_signal(cx, post.subscribed),
+        comments: post.counts.comments,
+        unread_comments: post.unread_comments,
+        media: None,
+        actions,
     };
+    let media_types = use_context::<MediaTypes>(cx).unwrap();
+    let embeds = use_context::<Embeds>(cx).unwrap();
+    // only links with nothing else to show are worth asking the server about
+    let detect_url = data
+        .post
+        .url
+        .as_ref()
+        .filter(|_| data.image.is_none() && !embeds.0.with_untracked(|e| data.has_embed(e)))
+        .map(|url| url.to_string());
+    // only changes when this link is detected, not whenever any link is
+    let media = create_memo(cx, move |_| {
+        detect_url.as_ref().and_then(|url| media_types.kind(url))
+    });
     let hide_read = use_context::<HideRead>(cx).unwrap();
+    let account = use_context::<Account>(cx).unwrap();
+    let preview = create_node_ref::<html::Div>(cx);
+    // a post open on its own page isn't being scrolled past in a feed
+    if !full {
+        let mark_read = use_context::<MarkRead>(cx).unwrap();
+        use_intersection_observer(cx, preview, move |entries, _| {
+            // only once it's gone off the top, not when it's still below the screen
+            if entries
+                .iter()
+                .any(|e| !e.is_intersecting() && e.bounding_client_rect().bottom() <= 0.0)
+            {
+                mark_read.post_seen(post_id, actions.state.get_untracked());
+            }
+        });
+    }
+    // big images is still the list layout, it only changes how images are sized
+    let layout = create_memo(cx, move |_| match view_mode.0() {
+        _ if full => ViewMode::Default,
+        ViewMode::BigImage => ViewMode::Default,
+        mode => mode,
+    });
     view! { cx,
-        <div class="flex flex-row bg-neutral-900 hover:border-neutral-700 p-1 border-neutral-500 border-b-4" class:hidden=move || read && hide_read.0() >
-            <Voter my_vote upvotes downvotes score />
-            <div class="flex flex-col">
-                <div class="flex flex-row gap-1">
-                    <PersonView person=creator/>
-                    "to"
-                    <CommunityBadge community subscribed />
-                    {locked
-                        .then(|| {
-                            view! { cx, <div class="bg-slate-500 px-2 rounded">"locked"</div> }
-                        })}
-                    {nsfw
-                        .then(|| {
-                            view! { cx, <div class="bg-red-600 px-2 rounded">"nsfw"</div> }
-                        })}
-                    <RelativeTime time=published/>
-                    {updated
-                        .map(|u| {
-                            view! { cx,
-                                "(updated "
-                                <RelativeTime time=u/>
-                                ")"
-                            }
-                        })}
-                    {featured_community.then(|| view!{cx, "📌"})}
-                    {featured_local.then(|| view!{cx, "📍"})}
-                    {read.then(|| view!{cx, <div class="px-2 rounded bg-gray-800">"read"</div>})}
-                </div>
-                <div class="flex flex-row">
-                    <div class="text-lg">{name}</div>
-                </div>
-                <div class="blur hidden"></div>
-                <div class:blur=move || { nsfw && global_blur.0() } class="hover:blur-none">
-                    {url.as_ref()
-                        .map(|url| {
-                            view! { cx,
-                                <a
-                                    target="_blank"
-                                    class="text-gray-500 hover:text-gray-400 underline"
-                                    href=url.to_string()
-                                >
-                                    {url.to_string()}
-                                </a>
-                            }
-                        })}
-                    {thumbnail_url
-                        .map(|url| {
-                            let (expanded, set_expanded) = create_signal(cx, false);
-                            view! { cx,
-                                <img
-                                    lazy="true"
-                                    on:click=move |_| set_expanded(!expanded())
-                                    class=move || {
-                                        if !expanded() && view_mode.0() == ViewMode::Default {
-                                            "max-h-96 max-w-96 object-scale-down"
-                                        } else {
-                                            "max-h-[calc(100vh-200px)] max-w-full min-h-96 min-w-96 object-scale-down"
-                                        }
-                                    }
-                                    src=url.to_string()
-                                />
-                            }
-                        })}
-                    {body
-                        .map(|body| {
-                            view! { cx, <ShowMore><Markdown content=body/></ShowMore> }
-                        })}
-                    {url.as_ref().map(|url| view!{cx, <MagicEmbed url=url.to_string() />})}
-                    <div class="bg-neutral-700 p-1 rounded" class:hidden=!has_embed>
-                        {embed_title
-                            .map(|title| {
-                                view! { cx, <div class="text-md">{title}</div> }
-                            })}
-                        {embed_description
-                            .map(|description| {
-                                view! { cx, <div class="text-sm">{description}</div> }
-                            })}
-                        {embed_video_url
-                            .map(|mut url| {
-                                match url.host_str(){ Some("yewtu.be" | "youtube.com") =>{url.query_pairs_mut().append_pair("autoplay", "0");} _ => {} }
-                                let src = url.to_string();
-                                if src.contains(".mp4") {
-                                    view!{cx, <VideoPlayer src/>}.into_view(cx)
-                                } else {
-
-                                    view! { cx, <iframe lazy="true" sandbox="allow-scripts allow-same-origin" allowfullscreen
-                                    frameborder="0" class="h-96 w-fit aspect-video" src=src></iframe> }.into_view(cx)
-                                }
-
-                            })}
-                        {url.map(|u| {let u = u.to_string();
-                            is_video(&u).then(||
-                                view!{cx, <VideoPlayer src=u />
-                            })})}
-                    </div>
-                </div>
-                <div class="flex flex-row gap-2 p-1 leading-none">
-                    <a
-                        class="text-gray-500 hover:text-gray-400 underline flex flex-row"
-                        href=format!("/post/{}", id.0)
-                    >
-                        {comments}
-                        " comments "
-                        {(comments != unread_comments && unread_comments != 0)
-                            .then(|| {
-                                view! { cx, <div class="text-red-300">"(" {unread_comments} " unread)"</div> }
-                            })}
-                    </a>
-                    <button class="text-gray-500 hover:text-gray-400 flex flex-row gap-1 underline" on:click=move |_| {
-                        let client = use_context::<CapyClient>(cx).unwrap();
-                        let current_instance = client.get_instance();
-                        let share_url = format!("{current_instance}/post/{}", post_id.0);
-                        let clipboard = window().navigator().clipboard().unwrap();
-                        let promise = clipboard.write_text(&share_url);
-                        spawn_local(async move {
-                            wasm_bindgen_futures::JsFuture::from(promise).await.unwrap();
-                        })
-                    }><Icon icon=MaybeSignal::Static(BsIcon::BsShareFill.into())/>"share"</button>
-                </div>
-            </div>
+        // posts marked read while scrolling stay visible, only the ones fetched as read are hidden
+        <div class:hidden=move || read && (hide_read.0() || !account.show_read_posts()) node_ref=preview>
+            {move || {
+                let mut data = data.clone();
+                data.media = media();
+                if data.media == Some(MediaKind::Image) {
+                    data.image = data.post.url.as_ref().map(|url| url.to_string());
+                }
+                match layout() {
+                    ViewMode::Compact => view! { cx, <CompactLayout data/> }.into_view(cx),
+                    ViewMode::Card => view! { cx, <CardLayout data/> }.into_view(cx),
+                    ViewMode::Gallery => view! { cx, <GalleryTile data/> }.into_view(cx),
+                    _ => view! { cx, <ListLayout data/> }.into_view(cx),
+                }
+            }}
         </div>
     }
 }
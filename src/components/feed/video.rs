@@ -0,0 +1,359 @@
+use std::{cell::RefCell, collections::HashMap, ops::Deref, rc::Rc};
+
+use capybara_lemmy_client::post::PostId;
+use js_sys::{Array, Function, Promise, Reflect};
+use leptos::{html::Video, *};
+use leptos_use::{use_intersection_observer_with_options, UseIntersectionObserverOptions};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::HtmlMediaElement;
+
+use super::{
+    lightbox::Lightbox,
+    post_preview::{GlobalViewMode, ViewMode},
+};
+
+/// Videos shorter than this loop, like gifs
+const SHORT_CLIP_SECONDS: f64 = 30.0;
+const SPEEDS: [f64; 6] = [0.5, 0.75, 1.0, 1.25, 1.5, 2.0];
+// put in the bundle by scripts/vendor-players.sh, bump the versions there and here together
+const HLS_SCRIPT: &str = "/public/vendor/hls-1.4.12.min.js";
+const DASH_SCRIPT: &str = "/public/vendor/dash.all-4.7.1.min.js";
+
+/// When videos in the feed start playing by themselves
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Autoplay {
+    #[default]
+    Always,
+    /// only on connections the browser doesn't report as metered or slow
+    DataSaver,
+    Never,
+}
+
+impl Autoplay {
+    pub const ALL: [Autoplay; 3] = [Autoplay::Always, Autoplay::DataSaver, Autoplay::Never];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Autoplay::Always => "always",
+            Autoplay::DataSaver => "data saver",
+            Autoplay::Never => "never",
+        }
+    }
+
+    fn allowed(&self) -> bool {
+        match self {
+            Autoplay::Always => true,
+            Autoplay::DataSaver => !metered_connection(),
+            Autoplay::Never => false,
+        }
+    }
+}
+
+/// Whether the browser says the user wants to save data, or is on a cellular or slow connection.
+/// Webviews without the Network Information API count as unmetered.
+fn metered_connection() -> bool {
+    let Ok(connection) = Reflect::get(&window().navigator(), &"connection".into()) else {
+        return false;
+    };
+    if connection.is_undefined() {
+        return false;
+    }
+    let field = |name: &str| Reflect::get(&connection, &name.into()).ok();
+    let save_data = field("saveData")
+        .and_then(|v| v.as_bool())
+        .unwrap_or_default();
+    let cellular = field("type")
+        .and_then(|v| v.as_string())
+        .is_some_and(|t| t == "cellular");
+    let slow = field("effectiveType")
+        .and_then(|v| v.as_string())
+        .is_some_and(|t| t != "4g");
+    save_data || cellular || slow
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoSettings {
+    pub autoplay: Autoplay,
+    pub muted: bool,
+    pub volume: f64,
+    pub speed: f64,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            autoplay: Autoplay::default(),
+            muted: false,
+            volume: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Video settings, mute, volume and speed are whatever they were last set to in any player
+#[derive(Clone, Copy)]
+pub struct Videos(pub RwSignal<VideoSettings>);
+
+impl Videos {
+    pub fn new(cx: Scope, settings: VideoSettings) -> Self {
+        Self(create_rw_signal(cx, settings))
+    }
+}
+
+/// Adaptive streams, which webviews mostly can't play without help
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Stream {
+    Hls,
+    Dash,
+}
+
+impl Stream {
+    fn of(src: &str) -> Option<Self> {
+        let path = Url::parse(src).ok()?.path().to_lowercase();
+        if path.ends_with(".m3u8") {
+            Some(Stream::Hls)
+        } else if path.ends_with(".mpd") {
+            Some(Stream::Dash)
+        } else {
+            None
+        }
+    }
+}
+
+thread_local! {
+    /// scripts added to the page, by their src
+    static SCRIPTS: RefCell<HashMap<&'static str, Promise>> = RefCell::new(HashMap::new());
+}
+
+/// Adds a script to the page the first time it's asked for, resolving once it has loaded
+fn load_script(src: &'static str) -> Promise {
+    SCRIPTS.with(|scripts| {
+        scripts
+            .borrow_mut()
+            .entry(src)
+            .or_insert_with(|| {
+                Promise::new(&mut |resolve, reject| {
+                    let script = document().create_element("script").unwrap();
+                    script.set_attribute("src", src).unwrap();
+                    let _ = Reflect::set(&script, &"onload".into(), &resolve);
+                    let _ = Reflect::set(&script, &"onerror".into(), &reject);
+                    let _ = document().body().unwrap().append_child(&script);
+                })
+            })
+            .clone()
+    })
+}
+
+fn call(target: &JsValue, method: &str, args: &[JsValue]) -> Result<JsValue, JsValue> {
+    let function: Function = Reflect::get(target, &method.into())?.dyn_into()?;
+    function.apply(target, &args.iter().collect::<Array>())
+}
+
+/// A player from hls.js or dash.js, and what tears it down
+type StreamPlayer = (JsValue, &'static str);
+
+/// Plays a manifest through hls.js or dash.js, unless the webview can play it itself
+async fn attach_stream(
+    video: HtmlMediaElement,
+    src: String,
+    stream: Stream,
+) -> Result<Option<StreamPlayer>, JsValue> {
+    // safari's webkit plays hls itself
+    let native_hls = !video
+        .can_play_type("application/vnd.apple.mpegurl")
+        .is_empty();
+    match stream {
+        Stream::Hls if native_hls => {
+            video.set_src(&src);
+            Ok(None)
+        }
+        Stream::Hls => {
+            JsFuture::from(load_script(HLS_SCRIPT)).await?;
+            let hls: Function = Reflect::get(&window(), &"Hls".into())?.dyn_into()?;
+            let player = Reflect::construct(&hls, &Array::new())?;
+            call(&player, "loadSource", &[src.into()])?;
+            call(&player, "attachMedia", &[video.into()])?;
+            Ok(Some((player, "destroy")))
+        }
+        Stream::Dash => {
+            JsFuture::from(load_script(DASH_SCRIPT)).await?;
+            let dashjs = Reflect::get(&window(), &"dashjs".into())?;
+            let factory = call(&dashjs, "MediaPlayer", &[])?;
+            let player = call(&factory, "create", &[])?;
+            call(
+                &player,
+                "initialize",
+                &[video.into(), src.into(), false.into()],
+            )?;
+            Ok(Some((player, "reset")))
+        }
+    }
+}
+
+fn media_element(video: HtmlElement<Video>) -> Option<HtmlMediaElement> {
+    let video = video.into_any();
+    let cast = video.deref().clone().dyn_into::<HtmlMediaElement>().ok();
+    cast
+}
+
+/// Points `video` at `src`, through a streaming player for HLS and DASH manifests
+pub fn load_source(cx: Scope, video: NodeRef<Video>, src: String) {
+    let player: Rc<RefCell<Option<StreamPlayer>>> = Rc::default();
+    let attached = player.clone();
+    video.on_load(cx, move |video| {
+        let Some(video) = media_element(video) else {
+            return;
+        };
+        let Some(stream) = Stream::of(&src) else {
+            video.set_src(&src);
+            return;
+        };
+        spawn_local(async move {
+            match attach_stream(video, src.clone(), stream).await {
+                Ok(player) => *attached.borrow_mut() = player,
+                Err(e) => log::warn!("couldn't play {src}: {e:?}"),
+            }
+        });
+    });
+    on_cleanup(cx, move || {
+        if let Some((player, teardown)) = player.borrow_mut().take() {
+            let _ = call(&player, teardown, &[]);
+        }
+    });
+}
+
+fn picture_in_picture_enabled() -> bool {
+    Reflect::get(&document(), &"pictureInPictureEnabled".into())
+        .ok()
+        .and_then(|enabled| enabled.as_bool())
+        .unwrap_or_default()
+}
+
+/// A video in the feed, playing while it's on screen if autoplay allows it
+#[component]
+pub fn VideoPlayer(
+    cx: Scope,
+    src: String,
+    /// lets the video be opened in the lightbox
+    #[prop(optional)]
+    post_id: Option<PostId>,
+) -> impl IntoView {
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    let videos = use_context::<Videos>(cx).unwrap();
+    let global_view_mode = use_context::<GlobalViewMode>(cx).unwrap();
+    let video_player = create_node_ref(cx);
+    load_source(cx, video_player, src);
+    let (visible, set_visible) = create_signal(cx, false);
+    use_intersection_observer_with_options(
+        cx,
+        video_player,
+        move |entry, _| {
+            set_visible(entry[0].is_intersecting());
+        },
+        UseIntersectionObserverOptions::default().thresholds(vec![0.8]),
+    );
+    let visible = create_memo(cx, move |_| visible());
+    let autoplay = create_memo(cx, move |_| videos.0.with(|s| s.autoplay.allowed()));
+    create_effect(cx, move |_| {
+        let Some(player) = video_player().and_then(media_element) else {
+            return;
+        };
+        // the lightbox plays its own copy
+        if !visible() || lightbox.is_open() {
+            let _ = player.pause();
+        } else if autoplay() {
+            let _ = player.play();
+        }
+    });
+    create_effect(cx, move |_| {
+        let Some(player) = video_player().and_then(media_element) else {
+            return;
+        };
+        videos.0.with(|s| {
+            player.set_muted(s.muted);
+            player.set_volume(s.volume);
+            // loading a source resets the rate to the default one
+            player.set_default_playback_rate(s.speed);
+            player.set_playback_rate(s.speed);
+        });
+    });
+    let on_volumechange = move |_| {
+        let Some(player) = video_player().and_then(media_element) else {
+            return;
+        };
+        let (muted, volume) = (player.muted(), player.volume());
+        let changed = videos
+            .0
+            .with_untracked(|s| s.muted != muted || s.volume != volume);
+        if changed {
+            videos.0.update(|s| {
+                s.muted = muted;
+                s.volume = volume;
+            });
+        }
+    };
+    let on_loadedmetadata = move |_| {
+        if let Some(player) = video_player().and_then(media_element) {
+            player.set_loop(player.duration() < SHORT_CLIP_SECONDS);
+        }
+    };
+    view! {cx,
+        <div class="relative w-fit group">
+            <video
+                controls
+                node_ref=video_player
+                preload=move || if autoplay() { "auto" } else { "metadata" }
+                class=move || match global_view_mode.0()  { ViewMode::BigImage => "min-h-96 min-w-96 max-h-[calc(100vh-200px)] max-w-full aspect-video", _ => "h-96 w-fit aspect-video" }
+                on:volumechange=on_volumechange
+                on:loadedmetadata=on_loadedmetadata
+            />
+            <div class="absolute top-1 right-1 flex flex-row gap-1 opacity-0 group-hover:opacity-100">
+                <select
+                    class="px-1 rounded bg-black/60 text-sm"
+                    title="playback speed"
+                    on:change=move |e| {
+                        if let Ok(speed) = event_target_value(&e).parse() {
+                            videos.0.update(|s| s.speed = speed);
+                        }
+                    }
+                >
+                    {SPEEDS
+                        .into_iter()
+                        .map(|speed| view! { cx,
+                            <option value=speed.to_string() prop:selected=move || videos.0.with(|s| s.speed == speed)>
+                                {format!("{speed}×")}
+                            </option>
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+                {picture_in_picture_enabled().then(|| view! { cx,
+                    <button
+                        class="px-1 rounded bg-black/60 hover:text-gray-400"
+                        title="picture in picture"
+                        on:click=move |_| {
+                            // not every webview has it, so it's looked up instead of bound
+                            if let Some(player) = video_player().and_then(media_element) {
+                                let _ = call(&player, "requestPictureInPicture", &[]);
+                            }
+                        }
+                    >
+                        "⧉"
+                    </button>
+                })}
+                {post_id.map(|post_id| view! { cx,
+                    <button
+                        class="px-1 rounded bg-black/60 hover:text-gray-400"
+                        title="open in lightbox"
+                        on:click=move |_| lightbox.open(post_id)
+                    >
+                        "⛶"
+                    </button>
+                })}
+            </div>
+        </div>
+    }
+}
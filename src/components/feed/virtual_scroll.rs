@@ -1,27 +1,19 @@
-use gloo::{
-    storage::{SessionStorage, Storage},
-    utils::{body, document},
-};
+use gloo::utils::{body, document};
 use leptos::*;
-use leptos_use::{
-    use_element_size, use_scroll_with_options, use_window_scroll, ScrollOffset, UseScrollOptions,
-    UseScrollReturn,
-};
+use leptos_use::{use_element_size, use_event_listener, use_window_scroll, UseElementSizeReturn};
 use log::info;
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Serialize};
 use std::{
-    collections::{hash_map::DefaultHasher, HashSet},
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
     future::Future,
     hash::{Hash, Hasher},
-    rc::Rc,
 };
-use web_sys::HtmlDivElement;
 
-#[derive(Serialize, Deserialize)]
-struct ScrollerData<T> {
-    y_scroll: f64,
-    data: Vec<T>,
-}
+use crate::{
+    components::feed::cache::{CachedFeed, FeedCache},
+    mutation::{ItemStore, StoreItem},
+    routing::replace_query_param,
+};
 
 #[component]
 pub fn InfinitePage<P, PFut, K, KF, VF, V, T, CK>(
@@ -31,52 +23,140 @@ pub fn InfinitePage<P, PFut, K, KF, VF, V, T, CK>(
     key: KF,
     view: VF,
     cache_key: CK,
+    /// the page `data` was fetched as, more are loaded from the one after
+    #[prop(default = 1)]
+    first_page: usize,
+    /// items are laid out in this many columns, shortest column first
+    #[prop(into, default = 1.into())]
+    columns: MaybeSignal<usize>,
+    /// keeps the page being read in the url's `page`, so reloading opens the feed there
+    #[prop(optional)]
+    page_in_url: bool,
 ) -> impl IntoView
 where
     P: Fn(usize) -> PFut + 'static + Copy,
     PFut: Future<Output = Vec<T>>,
-    T: 'static + Clone + DeserializeOwned + Serialize,
+    T: 'static + Clone + DeserializeOwned + Serialize + StoreItem,
     KF: Fn(&T) -> K + 'static + Copy,
-    K: Eq + Hash + 'static,
+    K: Eq + Hash + Clone + 'static,
     VF: Fn(Scope, T) -> V + 'static,
     V: IntoView,
     CK: Hash + Eq + PartialEq + 'static,
 {
     let mut hasher = DefaultHasher::new();
     cache_key.hash(&mut hasher);
-    let cache_key = hasher.finish();
+    let cache_key = format!("feed_{}", hasher.finish());
 
     // let scroller = create_node_ref(cx);
     let (hydrating, set_hydrating) = create_signal(cx, false);
-    let current_page = create_rw_signal(cx, 1);
+    let current_page = create_rw_signal(cx, first_page);
     let (at_end, set_at_end) = create_signal(cx, data.get_untracked().is_empty());
-    if let Ok(previous_session) = SessionStorage::get::<ScrollerData<T>>(cache_key.to_string()) {
-        let ScrollerData {
-            data: prev_data,
+    let heights = create_rw_signal(cx, HashMap::<K, f64>::new());
+    // the page each item was fetched as
+    let pages = store_value(cx, HashMap::<K, usize>::new());
+    data.with_untracked(|d| {
+        pages.update_value(|p| p.extend(d.iter().map(|item| (key(item), first_page))))
+    });
+    // items from a fresh first page that weren't there when the cached feed was saved
+    let new_items = create_rw_signal(cx, Vec::<T>::new());
+    if let Some(cached) = FeedCache::load::<T>(&cache_key) {
+        let stale = cached.is_stale();
+        let CachedFeed {
+            page,
             y_scroll,
-        } = previous_session;
-        data.update(|d| *d = prev_data);
+            data: mut prev_data,
+            heights: prev_heights,
+            pages: prev_pages,
+            ..
+        } = cached;
+        pages.update_value(|p| {
+            p.extend(
+                prev_data
+                    .iter()
+                    .zip(prev_pages)
+                    .filter_map(|(item, page)| Some((key(item), page?))),
+            )
+        });
+        heights.update(|h| {
+            h.extend(
+                prev_data
+                    .iter()
+                    .zip(prev_heights)
+                    .filter_map(|(item, height)| Some((key(item), height?))),
+            )
+        });
+        // `data` starts out as a freshly fetched first page
+        let fresh = data.get_untracked();
+        if stale && !fresh.is_empty() {
+            let mut fresh: HashMap<K, T> =
+                fresh.into_iter().map(|item| (key(&item), item)).collect();
+            for item in prev_data.iter_mut() {
+                if let Some(updated) = fresh.remove(&key(item)) {
+                    *item = updated;
+                }
+            }
+            let unseen = data.with_untracked(|d| {
+                d.iter()
+                    .filter(|item| fresh.contains_key(&key(item)))
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+            new_items.set(unseen);
+        }
+        current_page.set(page.max(first_page));
+        data.set(prev_data);
         request_animation_frame(move || {
             window().scroll_to_with_x_and_y(0.0, y_scroll);
         });
         info!("restored previous scrolling list {y_scroll}");
     }
+    // rows get narrower or wider with the column count, so their heights are off
+    create_effect(cx, move |prev: Option<usize>| {
+        let columns = columns.get();
+        if prev.is_some_and(|prev| prev != columns) {
+            heights.update(|h| h.clear());
+        }
+        columns
+    });
     let (_window_x, y_scroll) = use_window_scroll(cx);
+    let store = use_context::<ItemStore>(cx).unwrap();
     on_cleanup(cx, move || {
         let y_scroll = y_scroll.get_untracked();
-        let data = data.get_untracked();
-        let _ = SessionStorage::set(cache_key.to_string(), ScrollerData { y_scroll, data });
+        let mut data = data.get_untracked();
+        // votes and saves made while the feed was open aren't in the data we fetched
+        data.iter_mut().for_each(|item| item.refresh(&store));
+        let heights = heights
+            .with_untracked(|h| data.iter().map(|item| h.get(&key(item)).copied()).collect());
+        let pages =
+            pages.with_value(|p| data.iter().map(|item| p.get(&key(item)).copied()).collect());
+        FeedCache::save(
+            &cache_key,
+            CachedFeed::new(current_page.get_untracked(), y_scroll, data, heights, pages),
+        );
     });
+    let show_new_items = move || {
+        let unseen = new_items.get_untracked();
+        new_items.set(vec![]);
+        data.update(|data| {
+            let mut merged = unseen;
+            merged.append(data);
+            let mut dedup = HashSet::new();
+            merged.retain(|item| dedup.insert(key(item)));
+            *data = merged;
+        });
+        window().scroll_to_with_x_and_y(0.0, 0.0);
+    };
     let hydrate = move || {
         if !hydrating.get_untracked() && !at_end.get_untracked() {
             set_hydrating(true);
             spawn_local(async move {
                 current_page.update(|p| *p += 1);
                 let page = current_page.get_untracked();
-                let new_data = get_page(page as usize).await;
+                let new_data = get_page(page).await;
                 if new_data.is_empty() {
                     set_at_end(true);
                 }
+                pages.update_value(|p| p.extend(new_data.iter().map(|item| (key(item), page))));
                 data.update(|data| {
                     data.extend(new_data);
                     let mut dedup = HashSet::new();
@@ -87,6 +167,21 @@ where
         }
     };
 
+    let first_visible = create_rw_signal(cx, None::<K>);
+    if page_in_url {
+        let page = create_memo(cx, move |_| {
+            first_visible.with(|item| {
+                let item = item.as_ref()?;
+                pages.with_value(|p| p.get(item).copied())
+            })
+        });
+        create_effect(cx, move |_| {
+            if let Some(page) = page() {
+                replace_query_param(cx, "page", (page > 1).then(|| page.to_string()));
+            }
+        });
+    }
+
     let at_top = move || y_scroll() <= 100.0;
     let at_bottom = create_memo(cx, move |_| {
         let scroll_height = if let Some(scrolling_element) = document().scrolling_element() {
@@ -106,6 +201,7 @@ where
     // refresh effect
     create_effect(cx, move |_| {
         if y_scroll() < -30.0 && !hydrating() {
+            new_items.set(vec![]);
             data.update(|d| d.clear());
             current_page.set(0);
             hydrate();
@@ -129,23 +225,26 @@ where
     view! {cx,
 
         <button class="bg-gray-700 px-2 text-lg rounded-md" on:click=move |_| {
+            new_items.set(vec![]);
             data.update(|d| d.clear());
             current_page.set(0);
             hydrate();
         }>
             "Force Refresh"
         </button>
+        {move || {
+            let count = new_items.with(|n| n.len());
+            (count > 0).then(|| view! {cx,
+                <button class="bg-blue-700 px-2 text-lg fixed top-16 left-1/2 -translate-x-1/2 rounded-md hover:bg-blue-500 z-50" on:click=move |_| show_new_items()>
+                    {format!("{count} new since you left, show them")}
+                </button>
+            })
+        }}
         <button class:hidden=at_top class="bg-gray-700 px-2 text-lg fixed bottom-10 right-20 rounded-md hover:bg-gray-400 z-50" on:click=move |_| {
             info!("scrolled");
             window().scroll_to_with_x_and_y(0.0, 0.0);
         }>"Back to top"</button>
-        <div class="flex flex-col">
-            <For
-            each=data
-            key
-            view
-            />
-        </div>
+        <VirtualScroller each=data key view heights columns first_visible />
         {move || hydrating().then(|| view!{cx, "Loading!"})}
         {move || (!hydrating() && !at_end()).then(|| view!{cx, <button class="bg-gray-300 rounded px-3" on:click=move |_| {
             hydrate();
@@ -156,74 +255,173 @@ where
     }
 }
 
+/// Only renders the rows of `each` near the window's viewport. Rows are positioned absolutely
+/// within a container as tall as the whole list, each going into the currently shortest column.
+/// Row heights are measured as they render and unmeasured rows use `estimated_height`.
 #[component]
 pub fn VirtualScroller<T, D, V, KF, K>(
     cx: Scope,
-    each: Signal<Vec<T>>,
+    each: RwSignal<Vec<T>>,
     key: KF,
     view: D,
-    viewport_height: f64,
-    row_height: f64,
+    /// measured row heights, owned by the caller so they can be saved with the data
+    heights: RwSignal<HashMap<K, f64>>,
+    #[prop(into, default = 1.into())] columns: MaybeSignal<usize>,
+    #[prop(default = 400.0)] estimated_height: f64,
+    /// how far past each edge of the viewport rows are rendered, in pixels
+    #[prop(default = 1000.0)]
+    overscan_px: f64,
+    /// set to the first row at the top of the viewport
+    #[prop(optional)]
+    first_visible: Option<RwSignal<Option<K>>>,
 ) -> impl IntoView
 where
     D: Fn(Scope, T) -> V + 'static,
     V: IntoView,
-    KF: Fn(&T) -> K + 'static,
-    K: Eq + Hash + 'static,
+    KF: Fn(&T) -> K + 'static + Copy,
+    K: Eq + Hash + Clone + 'static,
     T: 'static + Clone,
 {
-    let render_ahead = 10;
-    let (scroll_offset, set_scroll_offset) = create_signal(cx, 0);
-    // use memo here so our signals only retrigger if the value actually changed.
-    let child_start = create_memo(cx, move |_| {
-        ((scroll_offset() as f64 / row_height) as u32).saturating_sub(render_ahead / 2)
+    let container = create_node_ref::<html::Div>(cx);
+    let (_window_x, y_scroll) = use_window_scroll(cx);
+    // the column and top of each row, and the height of the whole list
+    let layout = create_memo(cx, move |_| {
+        let columns = columns.get().max(1);
+        heights.with(|heights| {
+            each.with(|items| {
+                let mut column_heights = vec![0.0; columns];
+                let mut positions = HashMap::with_capacity(items.len());
+                for item in items {
+                    let key = key(item);
+                    let height = heights.get(&key).copied().unwrap_or(estimated_height);
+                    let (column, top) = column_heights
+                        .iter()
+                        .copied()
+                        .enumerate()
+                        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                        .unwrap_or_default();
+                    column_heights[column] = top + height;
+                    positions.insert(key, (column, top, height));
+                }
+                let total = column_heights.into_iter().fold(0.0, f64::max);
+                (positions, total)
+            })
+        })
     });
-    let children_shown = (viewport_height / row_height).ceil() as u32 + render_ahead;
-    create_effect(cx, move |_| {});
-    let virtual_children = move || {
-        each.with(|children| {
-            let array_size = children.len();
-            // make sure start + end doesn't go over the length of the vector
-            let start = (child_start() as usize).min(array_size);
-            let end = (child_start() + children_shown).min(array_size as u32) as usize;
-            children[start..end].to_vec()
+    let inner_height = || {
+        window()
+            .inner_height()
+            .ok()
+            .and_then(|h| h.as_f64())
+            .unwrap_or_default()
+    };
+    // a signal so resizing the window brings in the rows that now fit
+    let window_height = create_rw_signal(cx, inner_height());
+    let _ = use_event_listener(cx, window(), ev::resize, move |_| {
+        window_height.set(inner_height())
+    });
+    // the part of the list in the window
+    let viewport = move || {
+        let scroll = y_scroll();
+        let list_top = container
+            .get()
+            .map(|c| c.get_bounding_client_rect().top() + scroll)
+            .unwrap_or_default();
+        (scroll - list_top, scroll - list_top + window_height())
+    };
+    if let Some(first_visible) = first_visible {
+        create_effect(cx, move |_| {
+            let (view_top, _) = viewport();
+            let first = layout.with(|(positions, _)| {
+                each.with(|items| {
+                    items.iter().map(key).find(|item_key| {
+                        positions
+                            .get(item_key)
+                            .is_some_and(|(_, top, height)| top + height > view_top)
+                    })
+                })
+            });
+            if first_visible.with_untracked(|f| *f != first) {
+                first_visible.set(first);
+            }
+        });
+    }
+    let visible = move || {
+        let (view_top, view_bottom) = viewport();
+        let view_top = view_top - overscan_px;
+        let view_bottom = view_bottom + overscan_px;
+        layout.with(|(positions, _)| {
+            each.with(|items| {
+                items
+                    .iter()
+                    .filter(|item| {
+                        positions.get(&key(item)).is_some_and(|(_, top, height)| {
+                            top + height >= view_top && *top <= view_bottom
+                        })
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
         })
     };
-    view! {cx,
+    view! { cx,
+        // the browser's own scroll anchoring fights with the corrections made below
         <div
-        on:scroll= move |scroll| {
-            let div = event_target::<HtmlDivElement>(&scroll);
-            set_scroll_offset(div.scroll_top());
-        }
-      style=format!(r#"
-        height: {}px;
-        overflow: auto;
-      "#, viewport_height.ceil() as u32)
-    >
-      <div
-
-        style=move || {
-            format!(r#"
-          height: {}px;
-          overflow: hidden;
-          will-change: transform;
-          position: relative;
-        "#, (each.with(|children| children.len() + render_ahead as usize) as f64 * row_height).ceil() as u32)}
-      >
-        <div // offset for visible nodes
-          style=move || format!("
-            transform: translateY({}px);
-          ", (child_start() as f64 * row_height) as u32)
+            class="relative"
+            style=move || format!("overflow-anchor: none; height: {}px", layout.with(|(_, total)| *total))
+            node_ref=container
         >
-        // {move || virtual_children().into_iter().map(|child| view(cx, child)).collect::<Vec<_>>()}
-        // For component currently has issues. Possibly
-        // https://github.com/leptos-rs/leptos/issues/533
-        <For each=virtual_children
-         key=key
-         view=view
-        />
+            <For
+                each=visible
+                key
+                view=move |cx, item| {
+                    let row = create_node_ref::<html::Div>(cx);
+                    let row_key = key(&item);
+                    let UseElementSizeReturn { height, .. } = use_element_size(cx, row);
+                    // the first run is the size before the row is measured, after that rows can
+                    // be 0 high, like ones filtered out when they're shown
+                    create_effect(cx, move |measured: Option<()>| {
+                        let height = height();
+                        if measured.is_none() {
+                            return;
+                        }
+                        let old = heights
+                            .with_untracked(|h| h.get(&row_key).copied())
+                            .unwrap_or(estimated_height);
+                        if old == height {
+                            return;
+                        }
+                        heights.update(|h| {
+                            h.insert(row_key.clone(), height);
+                        });
+                        // keep what's on screen in place when a row above it changes size,
+                        // e.g. when its images finish loading. with several columns only
+                        // one of them moves, so there's nothing sensible to correct
+                        if columns.get_untracked() > 1 {
+                            return;
+                        }
+                        if let Some(row) = row.get_untracked() {
+                            let delta = height - old;
+                            if row.get_bounding_client_rect().bottom() - delta <= 0.0 {
+                                window().scroll_by_with_x_and_y(0.0, delta);
+                            }
+                        }
+                    });
+                    let position_key = key(&item);
+                    let style = move || {
+                        let columns = columns.get().max(1) as f64;
+                        let (column, top, _) = layout.with(|(positions, _)| {
+                            positions.get(&position_key).copied().unwrap_or_default()
+                        });
+                        format!(
+                            "position: absolute; top: {top}px; left: {}%; width: {}%",
+                            column as f64 * 100.0 / columns,
+                            100.0 / columns
+                        )
+                    };
+                    view! { cx, <div node_ref=row style=style>{view(cx, item)}</div> }
+                }
+            />
         </div>
-      </div>
-    </div>
     }
 }
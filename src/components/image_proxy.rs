@@ -0,0 +1,72 @@
+use std::collections::{HashMap, HashSet};
+
+use capybara_lemmy_client::CapyClient;
+use leptos::*;
+
+use super::offline::{scheme_url, Offline};
+
+/// An image url and the width it's wanted at
+type ProxyRequest = (String, Option<u32>);
+
+/// Loads images through the backend when turned on, so their hosts never see the user's
+/// address and every image is only downloaded once
+#[derive(Clone, Copy)]
+pub struct ImageProxy {
+    pub enabled: RwSignal<bool>,
+    cx: Scope,
+    /// cache keys of fetched images, None when the backend couldn't get one
+    proxied: RwSignal<HashMap<ProxyRequest, Option<String>>>,
+    /// images the backend is fetching
+    pending: StoredValue<HashSet<ProxyRequest>>,
+}
+
+impl ImageProxy {
+    pub fn new(cx: Scope, enabled: bool) -> Self {
+        Self {
+            enabled: create_rw_signal(cx, enabled),
+            cx,
+            proxied: create_rw_signal(cx, HashMap::new()),
+            pending: store_value(cx, HashSet::new()),
+        }
+    }
+
+    /// Where to load `url` from, shrunk to `width` if it's wider than that. None while the
+    /// backend is still fetching it
+    pub fn src(&self, url: &str, width: Option<u32>) -> Option<String> {
+        let offline = use_context::<Offline>(self.cx).unwrap();
+        if !self.enabled.get() {
+            return Some(offline.image_src(url));
+        }
+        let request = (url.to_string(), width);
+        match self.proxied.with(|p| p.get(&request).cloned()) {
+            Some(Some(key)) => Some(scheme_url("image-proxy", &key)),
+            // better to load it directly than to show nothing
+            Some(None) => Some(offline.image_src(url)),
+            None if offline.is_offline() => Some(offline.image_src(url)),
+            None => {
+                self.fetch(request);
+                None
+            }
+        }
+    }
+
+    fn fetch(&self, request: ProxyRequest) {
+        let mut new = false;
+        self.pending
+            .update_value(|p| new = p.insert(request.clone()));
+        if !new {
+            return;
+        }
+        let proxy = *self;
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(proxy.cx).unwrap();
+            let key = client.proxy_image(&request.0, request.1).await.ok();
+            proxy.pending.update_value(|p| {
+                p.remove(&request);
+            });
+            proxy.proxied.update(|p| {
+                p.insert(request, key);
+            });
+        });
+    }
+}
@@ -1,8 +1,11 @@
 use comrak::{markdown_to_html, ComrakOptions};
 use leptos::*;
 
+use crate::routing::open_in_app;
+
 #[component]
 pub fn Markdown(cx: Scope, content: String) -> impl IntoView {
     let content = markdown_to_html(&content, &ComrakOptions::default());
-    view! {cx, <div class="prose dark:prose-invert" inner_html=content></div>}
+    // links to lemmy pages open in the app
+    view! {cx, <div class="prose dark:prose-invert" inner_html=content on:click=move |e| open_in_app(cx, e)></div>}
 }
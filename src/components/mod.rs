@@ -1,8 +1,11 @@
 pub mod comments;
 pub mod community;
+pub mod editor;
 pub mod feed;
+pub mod image_proxy;
 pub mod markdown;
 pub mod numbers;
+pub mod offline;
 pub mod person;
 pub mod post;
 pub mod post_view_controls;
@@ -10,8 +13,10 @@ pub mod posts;
 pub mod profile;
 pub mod reply_box;
 pub mod save_button;
+pub mod share;
 pub mod show_more;
 pub mod sorting_components;
 pub mod subscribe;
 pub mod time;
+pub mod toast;
 pub mod voter;
@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+use capybara_lemmy_client::{offline::OfflineStatus, CapyClient};
+use leptos::*;
+
+use super::toast::Toasts;
+
+/// Whether the backend can reach the server, and how many changes are waiting to be sent
+#[derive(Clone, Copy)]
+pub struct Offline(pub RwSignal<OfflineStatus>);
+
+impl Offline {
+    pub fn new(cx: Scope) -> Self {
+        Self(create_rw_signal(cx, OfflineStatus::default()))
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.0.with(|s| s.offline)
+    }
+
+    /// Where to load an image from, downloaded copies are served by the backend while offline
+    pub fn image_src(&self, url: &str) -> String {
+        if !self.is_offline() {
+            return url.to_string();
+        }
+        let encoded = String::from(js_sys::encode_uri_component(url));
+        scheme_url("offline-image", &encoded)
+    }
+}
+
+/// A url for one of the backend's custom schemes
+pub fn scheme_url(scheme: &str, path: &str) -> String {
+    let windows = window()
+        .navigator()
+        .user_agent()
+        .map(|agent| agent.contains("Windows"))
+        .unwrap_or_default();
+    // windows webviews can't load custom schemes directly
+    if windows {
+        format!("https://{scheme}.localhost/{path}")
+    } else {
+        format!("{scheme}://localhost/{path}")
+    }
+}
+
+/// Shows when we're offline and sends queued changes once the connection is back
+#[component]
+pub fn OfflineIndicator(cx: Scope) -> impl IntoView {
+    let offline = use_context::<Offline>(cx).unwrap();
+    let toasts = use_context::<Toasts>(cx).unwrap();
+    let (syncing, set_syncing) = create_signal(cx, false);
+    let sync = move || {
+        if syncing.get_untracked() {
+            return;
+        }
+        set_syncing(true);
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            match client.sync_offline().await {
+                Ok(report) => {
+                    if report.sent > 0 {
+                        toasts.info(format!("sent {} changes made while offline", report.sent));
+                    }
+                    if report.failed > 0 {
+                        toasts.error(format!(
+                            "{} changes made while offline were rejected",
+                            report.failed
+                        ));
+                    }
+                }
+                Err(e) => toasts.error(e),
+            }
+            if let Ok(status) = client.offline_status().await {
+                offline.0.set(status);
+            }
+            set_syncing(false);
+        });
+    };
+    let refresh = move || {
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            if let Ok(status) = client.offline_status().await {
+                let was_offline = offline.0.with_untracked(|s| s.offline);
+                offline.0.set(status);
+                if was_offline && !status.offline && status.queued > 0 {
+                    sync();
+                }
+            }
+        });
+    };
+    refresh();
+    if let Ok(handle) = set_interval(refresh, Duration::from_secs(10)) {
+        on_cleanup(cx, move || handle.clear());
+    }
+    view! { cx,
+        {move || offline.is_offline().then(|| view! { cx,
+            <span class="bg-red-700 px-1 rounded">"offline"</span>
+        })}
+        {move || {
+            let queued = offline.0.with(|s| s.queued);
+            (queued > 0).then(|| view! { cx,
+                <button
+                    class="bg-neutral-800 px-1 rounded hover:bg-neutral-500"
+                    class:animate-pulse=syncing
+                    on:click=move |_| sync()
+                >
+                    {format!("{queued} waiting to send")}
+                </button>
+            })
+        }}
+    }
+}
+
+/// Saves what `requests` returns, urls of api responses and images, for reading offline
+#[component]
+pub fn DownloadOfflineButton<F>(cx: Scope, requests: F) -> impl IntoView
+where
+    F: Fn(&CapyClient) -> (Vec<String>, Vec<String>) + 'static,
+{
+    let toasts = use_context::<Toasts>(cx).unwrap();
+    let (downloading, set_downloading) = create_signal(cx, false);
+    let download = move |_| {
+        let client = use_context::<CapyClient>(cx).unwrap();
+        let (urls, images) = requests(&client);
+        set_downloading(true);
+        spawn_local(async move {
+            match client.download_offline(urls, images).await {
+                Ok(()) => toasts.info("saved for offline reading"),
+                Err(e) => toasts.error(e),
+            }
+            set_downloading(false);
+        });
+    };
+    view! { cx,
+        <button
+            class="bg-gray-700 px-2 rounded-md hover:bg-gray-400"
+            class:animate-pulse=downloading
+            prop:disabled=downloading
+            on:click=download
+        >
+            "download for offline"
+        </button>
+    }
+}
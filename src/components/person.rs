@@ -1,8 +1,26 @@
-use capybara_lemmy_client::person::Person;
+use capybara_lemmy_client::{
+    person::{GetPersonDetails, Person, PersonId},
+    CapyClient,
+};
 use leptos::*;
+use leptos_router::use_params_map;
+
+use crate::{
+    account::Account,
+    app::{CurrentUser, ErrorView},
+    blocks::Blocks,
+};
+
+use super::{feed::post_preview::PostPreview, image_proxy::ImageProxy};
 
 #[component]
 pub fn PersonView(cx: Scope, person: Person) -> impl IntoView {
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    let blocks = use_context::<Blocks>(cx).unwrap();
+    let proxy = use_context::<ImageProxy>(cx).unwrap();
+    let account = use_context::<Account>(cx).unwrap();
+    let (menu_open, set_menu_open) = create_signal(cx, false);
+    let menu_person = person.clone();
     let Person {
         name,
         avatar,
@@ -15,6 +33,7 @@ pub fn PersonView(cx: Scope, person: Person) -> impl IntoView {
         ..
     } = person;
     view! { cx,
+        <div class="flex flex-row relative">
         <a
             class="flex flex-row underline text-red-400 hover:text-red-600"
             class:bold=admin
@@ -23,7 +42,8 @@ pub fn PersonView(cx: Scope, person: Person) -> impl IntoView {
         >
             {avatar
                 .map(|a| {
-                    view! { cx, <img class="w-6 h-6 rounded-full p-r-2" src=a.to_string()/> }
+                    let a = a.to_string();
+                    view! { cx, <img class="w-6 h-6 rounded-full p-r-2" class:hidden=move || !account.show_avatars() src=move || proxy.src(&a, Some(48))/> }
                 })}
             <div class="group font-lg flex flex-row">
                 {name} {(!local)
@@ -35,5 +55,79 @@ pub fn PersonView(cx: Scope, person: Person) -> impl IntoView {
                     })}
             </div>
         </a>
+        {move || current_user.with(|u| u.is_some()).then(|| {
+            let menu_person = menu_person.clone();
+            view! { cx,
+                <button class="px-1 text-gray-500 hover:text-gray-300" on:click=move |_| set_menu_open(!menu_open())>
+                    "..."
+                </button>
+                <div class="flex flex-col absolute top-6 left-0 z-10 bg-neutral-800 rounded p-1" class:hidden=move || !menu_open()>
+                    <button
+                        class="hover:bg-neutral-600 px-1 rounded text-left"
+                        on:click=move |_| {
+                            let blocked = blocks.is_person_blocked(menu_person.id);
+                            blocks.block_person(cx, menu_person.clone(), !blocked);
+                            set_menu_open(false);
+                        }
+                    >
+                        {move || if blocks.is_person_blocked(id) { "unblock user" } else { "block user" }}
+                    </button>
+                </div>
+            }
+        })}
+        </div>
+    }
+}
+
+/// A person's page, `/person/:person` takes an id or a name like `name@instance`
+#[component]
+pub fn PersonPage(cx: Scope) -> impl IntoView {
+    let params = use_params_map(cx);
+    let person = create_memo(cx, move |_| params.with(|p| p.get("person").cloned()));
+    let details = create_local_resource(cx, person, move |person| async move {
+        let client = use_context::<CapyClient>(cx).unwrap();
+        let person = person.unwrap_or_default();
+        let (person_id, username) = match person.parse() {
+            Ok(id) => (Some(PersonId(id)), None),
+            Err(_) => (None, Some(person)),
+        };
+        client
+            .execute(GetPersonDetails {
+                person_id,
+                username,
+                ..Default::default()
+            })
+            .await
+            .map_err(leptos::error::Error::from)
+    });
+    view! { cx,
+        <Suspense fallback=move || view! { cx, "Loading" }>
+            {move || details.with(cx, move |details| view! { cx,
+                <ErrorView
+                    value=details.clone()
+                    ok=move |details| {
+                        let counts = details.person_view.counts;
+                        view! { cx,
+                            <div class="flex flex-col gap-2">
+                                <div class="text-2xl">
+                                    <PersonView person=details.person_view.person/>
+                                </div>
+                                <div class="text-gray-500">
+                                    {format!(
+                                        "{} posts, {} comments",
+                                        counts.post_count, counts.comment_count
+                                    )}
+                                </div>
+                                {details
+                                    .posts
+                                    .into_iter()
+                                    .map(|post| view! { cx, <PostPreview post/> })
+                                    .collect::<Vec<_>>()}
+                            </div>
+                        }
+                    }
+                />
+            })}
+        </Suspense>
     }
 }
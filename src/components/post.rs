@@ -1,5 +1,13 @@
-use crate::components::{comments::PostComments, feed::post_preview::PostPreview};
+use crate::components::{
+    comments::{PostComments, COMMENT_PAGE_SIZE},
+    feed::{
+        lightbox::Lightbox,
+        post_preview::{preview_image, PostPreview},
+    },
+    offline::DownloadOfflineButton,
+};
 use capybara_lemmy_client::{
+    comment::{CommentSortType, GetComments},
     post::{GetPost, PostId},
     CapyClient,
 };
@@ -41,6 +49,41 @@ pub fn Post(cx: Scope) -> impl IntoView {
         >
             "BACK"
         </button>
+        <DownloadOfflineButton requests=move |client: &CapyClient| {
+            let Some(id) = post_id.get_untracked() else {
+                return (vec![], vec![]);
+            };
+            let post = resource.read(cx).flatten();
+            // every page of comments, not just the first
+            let comment_pages = post
+                .as_ref()
+                .map(|post| post.post_view.counts.comments.max(1) as u64)
+                .unwrap_or(1)
+                .div_ceil(COMMENT_PAGE_SIZE as u64);
+            // the same requests the post page makes, so their responses are found offline
+            let comments = (1..=comment_pages).map(|page| {
+                client.request_url(GetComments {
+                    post_id: Some(PostId(id)),
+                    sort: Some(CommentSortType::Hot),
+                    limit: Some(COMMENT_PAGE_SIZE),
+                    page: (page > 1).then_some(page as i64),
+                    ..Default::default()
+                })
+            });
+            let urls: Vec<String> = [client.request_url(GetPost {
+                id: Some(PostId(id)),
+                ..Default::default()
+            })]
+            .into_iter()
+            .chain(comments)
+            .filter_map(Result::ok)
+            .collect();
+            let images: Vec<String> = post
+                .and_then(|post| preview_image(&post.post_view.post))
+                .into_iter()
+                .collect();
+            (urls, images)
+        }/>
         <Suspense fallback=move || {
             view! { cx, "Loading" }
         }>
@@ -50,11 +93,19 @@ pub fn Post(cx: Scope) -> impl IntoView {
                     .map(|post_opt| {
                         post_opt
                             .map(|post| {
-                                view! { cx, <PostPreview post=post.post_view/> }
+                                // the lightbox only has this post to show
+                                let lightbox = use_context::<Lightbox>(cx).unwrap();
+                                lightbox.set_feed(cx, create_rw_signal(cx, vec![post.post_view.clone()]));
+                                view! { cx, <PostPreview post=post.post_view full=true/> }
                             })
                     })
             }}
         </Suspense>
-        {move || post_id().map(|p| view!{cx, <PostComments post_id=PostId(p) />})}
+        {move || post_id().map(|p| view!{cx,
+            <PostComments
+                post_id=PostId(p)
+                post=Signal::derive(cx, move || resource.read(cx).flatten().map(|p| p.post_view))
+            />
+        })}
     }
 }
@@ -1,21 +1,45 @@
 use leptos::*;
 
-use crate::{app::HideRead, components::feed::post_preview::ViewMode};
+use crate::{
+    app::HideRead,
+    components::feed::post_preview::ViewMode,
+    nsfw::{Nsfw, NsfwPolicy, NsfwPolicySelect},
+    preferences::PreferenceStore,
+};
 
-use super::feed::post_preview::{GlobalBlurState, GlobalViewMode};
+use super::{
+    feed::{
+        mark_read::MarkRead,
+        post_preview::GlobalViewMode,
+        video::{Autoplay, Videos},
+    },
+    image_proxy::ImageProxy,
+};
 
 #[component]
-pub fn PostViewControls(cx: Scope) -> impl IntoView {
-    let blur_nsfw = use_context::<GlobalBlurState>(cx).unwrap();
+pub fn PostViewControls(
+    cx: Scope,
+    /// where the layout picked here is remembered, see `PreferenceStore::view_mode`
+    #[prop(into)]
+    layout_key: Signal<String>,
+) -> impl IntoView {
+    let nsfw = use_context::<Nsfw>(cx).unwrap();
+    let preferences = use_context::<PreferenceStore>(cx).unwrap();
     let post_view_mode = use_context::<GlobalViewMode>(cx).unwrap();
     let hide_read = use_context::<HideRead>(cx).unwrap();
+    let mark_read = use_context::<MarkRead>(cx).unwrap();
+    let proxy = use_context::<ImageProxy>(cx).unwrap();
+    let videos = use_context::<Videos>(cx).unwrap();
     view! {cx,
     <div class="flex flex-row gap-2">
         <div class="flex flex-row">
-            <label for="blurnsfw">"blur nsfw:"</label>
-            <input type="checkbox" id="blurnsfw" name="blurnsfw" value="NSFW" prop:checked=blur_nsfw.0 on:click=move |_| {
-                blur_nsfw.0.update(|nsfw| {*nsfw = !*nsfw;});
-            }/>
+            <a class="underline" href="/nsfw">"nsfw:"</a>
+            <NsfwPolicySelect
+                policy=Signal::derive(cx, move || Some(nsfw.settings.with(|s| s.global)))
+                on_change=move |policy: Option<NsfwPolicy>| {
+                    nsfw.settings.update(|s| s.global = policy.unwrap_or_default())
+                }
+            />
         </div>
         <div class="flex flex-row">
             <label for="hideread">"hide read:"</label>
@@ -24,12 +48,51 @@ pub fn PostViewControls(cx: Scope) -> impl IntoView {
             }/>
         </div>
         <div class="flex flex-row">
-            <button class="bg-neutral-800 hover:bg-neutral-500 border-gray-300 border-b-1 p-1 align-bottom" on:click=move |_| { post_view_mode.0.set(ViewMode::Default) } >
-                "default"
-            </button>
-            <button class="bg-neutral-800 hover:bg-neutral-500 border-gray-300 border-b-1 p-1 align-bottom" on:click=move |_| { post_view_mode.0.set(ViewMode::BigImage) } >
-                "big images"
-            </button>
+            <label for="markread">"mark read on scroll:"</label>
+            <input type="checkbox" id="markread" name="markread" prop:checked=mark_read.enabled on:click=move |_| {
+                mark_read.enabled.update(|enabled| {*enabled = !*enabled;});
+            }/>
+        </div>
+        <div class="flex flex-row">
+            <label for="proxyimages">"proxy images:"</label>
+            <input type="checkbox" id="proxyimages" name="proxyimages" prop:checked=proxy.enabled on:click=move |_| {
+                proxy.enabled.update(|enabled| {*enabled = !*enabled;});
+            }/>
+        </div>
+        <div class="flex flex-row">
+            <label for="autoplay">"autoplay:"</label>
+            <select id="autoplay" class="bg-neutral-800" on:change=move |e| {
+                let value = event_target_value(&e);
+                if let Some(autoplay) = Autoplay::ALL.into_iter().find(|a| a.label() == value) {
+                    videos.0.update(|s| s.autoplay = autoplay);
+                }
+            }>
+                {Autoplay::ALL
+                    .into_iter()
+                    .map(|autoplay| view! { cx,
+                        <option value=autoplay.label() prop:selected=move || videos.0.with(|s| s.autoplay == autoplay)>
+                            {autoplay.label()}
+                        </option>
+                    })
+                    .collect::<Vec<_>>()}
+            </select>
+        </div>
+        <div class="flex flex-row">
+            {ViewMode::ALL
+                .into_iter()
+                .map(|mode| view! { cx,
+                    <button
+                        class="bg-neutral-800 hover:bg-neutral-500 border-gray-300 border-b-1 p-1 align-bottom"
+                        class:underline=move || post_view_mode.0() == mode
+                        on:click=move |_| {
+                            post_view_mode.0.set(mode);
+                            preferences.set_view_mode(&layout_key.get_untracked(), mode);
+                        }
+                    >
+                        {mode.label()}
+                    </button>
+                })
+                .collect::<Vec<_>>()}
         </div>
     </div>}
 }
@@ -1,36 +1,116 @@
 use capybara_lemmy_client::{
-    post::{GetPosts, PostView},
+    post::{GetPosts, ListingType, PostView, SortType},
     CapyClient,
 };
 use leptos::*;
 
 use crate::{
+    account::Account,
     app::{CurrentUser, ErrorView},
+    blocks::Blocks,
     community::CommunityKey,
     components::{
-        feed::{post_preview::PostPreview, virtual_scroll::InfinitePage},
+        feed::{
+            lightbox::Lightbox,
+            mark_read::UndoMarkRead,
+            post_preview::{preview_image, GlobalViewMode, PostPreview},
+            virtual_scroll::InfinitePage,
+        },
+        offline::DownloadOfflineButton,
         post_view_controls::PostViewControls,
         sorting_components::{SortMenu, TypeMenu},
     },
+    filters::{ContentFilters, FilterAction, FilteredNotice},
+    nsfw::Nsfw,
+    preferences::PreferenceStore,
+    routing::{query_param, query_writer, set_query_param},
 };
 
+/// The first page is fetched without a page number
+fn page_number(page: usize) -> Option<i64> {
+    (page > 1).then_some(page as i64)
+}
+
 #[component]
 pub fn Posts(
     cx: Scope,
     #[prop(into, optional)] community: Option<Signal<Option<CommunityKey>>>,
 ) -> impl IntoView {
-    let (sort, set_sort) = create_signal(cx, None);
-    let (type_, set_type) = create_signal(cx, None);
+    let preferences = use_context::<PreferenceStore>(cx).unwrap();
     let user = use_context::<CurrentUser>(cx).unwrap();
+    let filters = use_context::<ContentFilters>(cx).unwrap();
+    let blocks = use_context::<Blocks>(cx).unwrap();
+    let nsfw = use_context::<Nsfw>(cx).unwrap();
+    let account = use_context::<Account>(cx).unwrap();
+    let view_mode = use_context::<GlobalViewMode>(cx).unwrap();
+    let lightbox = use_context::<Lightbox>(cx).unwrap();
+    // each community remembers its own layout
+    let layout_key = Signal::derive(cx, move || {
+        community
+            .and_then(|c| c())
+            .map(|c| c.storage_key())
+            .unwrap_or_else(|| "front".to_string())
+    });
+    create_effect(cx, move |_| {
+        view_mode.0.set(preferences.view_mode(&layout_key()))
+    });
+    // the query string wins so feeds can be linked, then what was picked in the community, then
+    // the app's defaults and then the account's or site's
+    let query_sort = query_param::<SortType>(cx, "sort");
+    let query_type = query_param::<ListingType>(cx, "type");
+    // feeds opened at a later page carry on from there
+    let query_page = query_param::<usize>(cx, "page");
+    let first_page = create_memo(cx, move |_| query_page().unwrap_or(1).max(1));
+    let sort = create_memo(cx, move |_| {
+        query_sort()
+            .or_else(|| {
+                community
+                    .and_then(|c| c())
+                    .and_then(|c| preferences.community_sort(&c.storage_key()))
+            })
+            .or_else(|| preferences.with(|p| p.default_sort))
+            .or_else(|| account.default_sort())
+    });
+    let type_ = create_memo(cx, move |_| {
+        query_type()
+            .or_else(|| preferences.with(|p| p.default_listing_type))
+            .or_else(|| account.default_listing_type())
+    });
+    // the menus only write, what's shown comes from the url
+    let set_type = query_writer::<ListingType>(cx, "type");
+    let (picked_sort, set_sort) = create_signal(cx, None);
+    create_effect(cx, move |first: Option<()>| {
+        let sort = picked_sort();
+        if first.is_some() {
+            if let Some(community) = community.and_then(|c| c.get_untracked()) {
+                preferences.set_community_sort(&community.storage_key(), sort);
+            }
+            set_query_param(cx, "sort", sort.map(|s| s.to_string()));
+        }
+    });
+    // the account's defaults come with the site, fetching before it has loaded would only fetch
+    // again once it has
     let posts = create_local_resource(
         cx,
-        move || (sort(), type_(), user.0(), community.and_then(|c| c())),
-        move |(sort, type_, _, community)| async move {
+        move || {
+            account.loaded().then(|| {
+                (
+                    sort(),
+                    type_(),
+                    first_page(),
+                    user.0(),
+                    community.and_then(|c| c()),
+                )
+            })
+        },
+        move |source| async move {
+            let (sort, type_, first_page, _, community) = source?;
             let client = use_context::<CapyClient>(cx).expect("need client");
             let community_name = community.as_ref().and_then(|c| c.name());
             let community_id = community.as_ref().and_then(|c| c.id());
-            client
+            let posts = client
                 .execute(GetPosts {
+                    page: page_number(first_page),
                     sort,
                     type_,
                     community_name,
@@ -38,7 +118,8 @@ pub fn Posts(
                     ..Default::default()
                 })
                 .await
-                .map_err(leptos::error::Error::from)
+                .map_err(leptos::error::Error::from);
+            Some(posts)
         },
     );
     view! { cx,
@@ -46,8 +127,9 @@ pub fn Posts(
                 <SortMenu sort set_sort/>
                 <TypeMenu type_ set_type/>
                 <div class="w-5"></div>
-                <PostViewControls />
+                <PostViewControls layout_key/>
         </div>
+        <UndoMarkRead/>
         <div class="flex flex-col">
             
             <Suspense fallback=move || {
@@ -58,6 +140,9 @@ pub fn Posts(
                         .with(
                             cx,
                             move |p| {
+                                let Some(p) = p else {
+                                    return view! { cx, "Loading" }.into_view(cx);
+                                };
                                 view! { cx,
                                     <ErrorView
                                         value=p.clone()
@@ -65,11 +150,36 @@ pub fn Posts(
                                             let posts = p.posts;
                                             let sort = sort();
                                             let type_ = type_();
+                                            let first_page = first_page();
                                             let community_temp = community.and_then(|c| c.get());
                                             let community_id = community_temp.as_ref().and_then(|id| id.id());
                                             let community_name = community_temp.as_ref().and_then(|name| name.name());
                                             let posts = create_rw_signal(cx, posts);
+                                            lightbox.set_feed(cx, posts);
+                                            let community_name_2 = community_name.clone();
                                             view! { cx,
+                                                <DownloadOfflineButton requests=move |client: &CapyClient| {
+                                                    let urls: Vec<String> = (first_page..first_page + 3)
+                                                        .map(page_number)
+                                                        .filter_map(|page| {
+                                                            client
+                                                                .request_url(GetPosts {
+                                                                    page,
+                                                                    type_,
+                                                                    sort,
+                                                                    community_id,
+                                                                    community_name: community_name_2.clone(),
+                                                                    ..Default::default()
+                                                                })
+                                                                .ok()
+                                                        })
+                                                        .collect();
+                                                    let images: Vec<String> = posts
+                                                        .with_untracked(|p| {
+                                                            p.iter().filter_map(|p| preview_image(&p.post)).collect()
+                                                        });
+                                                    (urls, images)
+                                                }/>
                                                 <InfinitePage
                                                     get_page=move |page| async move {
                                                         let client = use_context::<CapyClient>(cx).expect("need client");
@@ -90,16 +200,46 @@ pub fn Posts(
                                                             .posts
                                                     }
                                                     key=move |p: &PostView| p.post.id
-                                                    view=move |cx, post| {
-                                                        view! { cx, <PostPreview post/> }
+                                                    view=move |cx, post: PostView| {
+                                                        // filtered when shown rather than dropped from the feed, so
+                                                        // changing the filters, unblocking, a looser nsfw policy or showing
+                                                        // bots brings posts back
+                                                        let filtered = create_memo(cx, {
+                                                            let post = post.clone();
+                                                            move |_| filters.post(&post)
+                                                        });
+                                                        let hidden = create_memo(cx, {
+                                                            let post = post.clone();
+                                                            move |_| {
+                                                                blocks.hides_post(&post)
+                                                                    || nsfw.hides_post(&post)
+                                                                    || account.hides_creator(&post.creator)
+                                                            }
+                                                        });
+                                                        move || match filtered() {
+                                                            _ if hidden() => ().into_view(cx),
+                                                            Some(filter) if filter.action == FilterAction::Collapse => view! { cx,
+                                                                <FilteredNotice filter>
+                                                                    <PostPreview post=post.clone()/>
+                                                                </FilteredNotice>
+                                                            }.into_view(cx),
+                                                            Some(_) => ().into_view(cx),
+                                                            None => view! { cx, <PostPreview post=post.clone()/> }.into_view(cx),
+                                                        }
                                                     }
                                                     data=posts
+                                                    first_page
+                                                    page_in_url=true
+                                                    columns=Signal::derive(cx, move || view_mode.0().columns())
+                                                    // not keyed by the page, it's in the url
+                                                    // while scrolling and the feed already holds it
                                                     cache_key=(("posts", sort, type_, community_id, community_name))
                                                 />
                                             }
                                         }
                                     />
                                 }
+                                .into_view(cx)
                             },
                         )
                 }}
@@ -1,12 +1,27 @@
+use std::collections::HashMap;
+
 use capybara_lemmy_client::{
-    comment::{CommentId, CreateComment},
-    post::PostId,
+    comment::{Comment, CommentAggregates, CommentId, CommentView, CreateComment},
+    community::SubscribedType,
+    error::ClientError,
+    post::{LanguageId, PostId, PostView},
     CapyClient,
 };
+use chrono::Utc;
 use leptos::*;
 use leptos_icons::{BsIcon, Icon};
 
-use super::comments::CommentWithChildren;
+use super::{
+    comments::CommentWithChildren,
+    editor::{quote_markdown, MarkdownEditor},
+    time::RelativeTime,
+    toast::Toasts,
+};
+use crate::{
+    account::Account,
+    app::CurrentUser,
+    drafts::{Draft, DraftKey, DraftTarget, Drafts},
+};
 
 #[component]
 pub fn ReplyButton(
@@ -32,6 +47,246 @@ pub fn ReplyButton(
     </div>}
 }
 
+/// A reply that has been sent, but that the server hasn't confirmed yet. It sits in the thread
+/// as a placeholder comment with a made up negative id until the server's comment replaces it.
+#[derive(Clone, PartialEq)]
+struct PendingReply {
+    /// sent along with the request so a retry doesn't post it twice
+    form_id: String,
+    parent_id: Option<CommentId>,
+    content: String,
+    /// the error if sending failed
+    error: Option<String>,
+    /// we're offline and the backend will send it once we're back
+    queued: bool,
+}
+
+/// Lemmy's length limit for comments. It's built into the server rather than a site setting, so
+/// there's nothing to read it from.
+const COMMENT_MAX_LENGTH: usize = 10_000;
+
+fn new_form_id() -> String {
+    format!(
+        "capy-{}-{}",
+        Utc::now().timestamp_millis(),
+        (js_sys::Math::random() * 1_000_000.0) as u32
+    )
+}
+
+/// Replies to the post being read that are on their way, by the id of their placeholder. They're
+/// kept for the whole post rather than in a reply box, which goes away when its comment is
+/// scrolled out of view.
+#[derive(Clone, Copy)]
+pub struct PendingReplies {
+    replies: RwSignal<HashMap<CommentId, PendingReply>>,
+    post_id: PostId,
+    /// placeholders are made from it, None until it has loaded
+    post: Signal<Option<PostView>>,
+    next_id: StoredValue<i32>,
+    /// the draft of a failed reply taken back for editing, its reply box opens with it
+    editing: RwSignal<Option<DraftKey>>,
+}
+
+impl PendingReplies {
+    pub fn new(cx: Scope, post_id: PostId, post: Signal<Option<PostView>>) -> Self {
+        Self {
+            replies: create_rw_signal(cx, HashMap::new()),
+            post_id,
+            post,
+            next_id: store_value(cx, 0),
+            editing: create_rw_signal(cx, None),
+        }
+    }
+
+    pub fn is_placeholder(id: CommentId) -> bool {
+        id.0 < 0
+    }
+
+    fn draft_key(cx: Scope, post_id: PostId, parent_id: Option<CommentId>) -> DraftKey {
+        let current_user = use_context::<CurrentUser>(cx).unwrap();
+        DraftKey::new(
+            current_user.get_untracked().as_ref(),
+            DraftTarget::Reply { post_id, parent_id },
+        )
+    }
+
+    /// A comment by the current account standing in for a reply
+    fn placeholder(
+        &self,
+        cx: Scope,
+        parent_path: Option<String>,
+        content: String,
+    ) -> Option<CommentView> {
+        let account = use_context::<Account>(cx).unwrap();
+        let creator = account
+            .user
+            .with_untracked(|u| u.as_ref().map(|u| u.person.clone()))?;
+        let PostView {
+            post, community, ..
+        } = self.post.get_untracked()?;
+        let mut id = 0;
+        self.next_id.update_value(|next| {
+            *next -= 1;
+            id = *next;
+        });
+        let id = CommentId(id);
+        let now = Utc::now().naive_utc();
+        Some(CommentView {
+            comment: Comment {
+                id,
+                creator_id: creator.id,
+                post_id: post.id,
+                content,
+                removed: false,
+                published: now,
+                updated: None,
+                deleted: false,
+                ap_id: post.ap_id.clone(),
+                local: true,
+                path: format!(
+                    "{}.{}",
+                    parent_path.unwrap_or_else(|| "0".to_string()),
+                    id.0
+                ),
+                distinguished: false,
+                language_id: LanguageId(0),
+            },
+            creator,
+            post,
+            community,
+            counts: CommentAggregates {
+                id: id.0,
+                comment_id: id,
+                score: 0,
+                upvotes: 0,
+                downvotes: 0,
+                published: now,
+                child_count: 0,
+                hot_rank: 0,
+            },
+            creator_banned_from_community: false,
+            subscribed: SubscribedType::NotSubscribed,
+            saved: false,
+            creator_blocked: false,
+            my_vote: None,
+        })
+    }
+
+    /// Puts a placeholder for the reply at the top of its parent's replies and sends it
+    fn submit(
+        &self,
+        cx: Scope,
+        thread: RwSignal<Vec<CommentWithChildren>>,
+        parent_id: Option<CommentId>,
+        content: String,
+    ) {
+        let parent_path = parent_id.and_then(|parent| {
+            thread.with_untracked(|t| {
+                CommentWithChildren::find(t, parent).map(|c| c.0.comment.path.clone())
+            })
+        });
+        let Some(placeholder) = self.placeholder(cx, parent_path, content.clone()) else {
+            // the draft is still there to send once logged in
+            if let Some(toasts) = use_context::<Toasts>(cx) {
+                toasts.error("log in to reply");
+            }
+            return;
+        };
+        let id = placeholder.comment.id;
+        self.replies.update(|r| {
+            r.insert(
+                id,
+                PendingReply {
+                    form_id: new_form_id(),
+                    parent_id,
+                    content,
+                    error: None,
+                    queued: false,
+                },
+            );
+        });
+        thread.update(|t| {
+            if let Some(children) = CommentWithChildren::children_mut(t, parent_id) {
+                children.insert(0, CommentWithChildren(placeholder, vec![]));
+            }
+        });
+        self.send(cx, thread, id);
+    }
+
+    /// Sends the reply behind placeholder `id`, the server's comment takes the placeholder's
+    /// place in the thread
+    fn send(&self, cx: Scope, thread: RwSignal<Vec<CommentWithChildren>>, id: CommentId) {
+        let replies = self.replies;
+        let Some(reply) = replies.with_untracked(|r| r.get(&id).cloned()) else {
+            return;
+        };
+        let post_id = self.post_id;
+        let update = move |f: &dyn Fn(&mut PendingReply)| {
+            replies.update(|r| {
+                if let Some(reply) = r.get_mut(&id) {
+                    f(reply);
+                }
+            })
+        };
+        update(&|r| {
+            r.error = None;
+            r.queued = false;
+        });
+        let key = Self::draft_key(cx, post_id, reply.parent_id);
+        let request = CreateComment {
+            content: reply.content,
+            post_id,
+            parent_id: reply.parent_id,
+            form_id: Some(reply.form_id),
+            ..Default::default()
+        };
+        // the row it was sent from can be scrolled away before the response arrives
+        let client = use_context::<CapyClient>(cx).unwrap();
+        spawn_local(async move {
+            match client.execute(request).await {
+                Ok(response) => {
+                    // the draft is kept around until the server has the comment
+                    Drafts::remove(&key);
+                    replies.update(|r| {
+                        r.remove(&id);
+                    });
+                    let comment_view = response.comment_view;
+                    thread.update(|t| {
+                        if CommentWithChildren::find(t, comment_view.comment.id).is_some() {
+                            CommentWithChildren::remove(t, id);
+                        } else if let Some(placeholder) = CommentWithChildren::find_mut(t, id) {
+                            placeholder.0 = comment_view;
+                        }
+                    });
+                }
+                Err(ClientError::QueuedOffline) => {
+                    Drafts::remove(&key);
+                    update(&|r| r.queued = true);
+                }
+                Err(e) => {
+                    let error = e.to_string();
+                    update(&|r| r.error = Some(error.clone()));
+                }
+            }
+        });
+    }
+
+    /// Takes a reply that failed to send out of the thread and back into its reply box
+    fn edit(&self, cx: Scope, thread: RwSignal<Vec<CommentWithChildren>>, id: CommentId) {
+        let Some(reply) = self.replies.with_untracked(|r| r.get(&id).cloned()) else {
+            return;
+        };
+        let post_id = self.post_id;
+        self.replies.update(|r| {
+            r.remove(&id);
+        });
+        thread.update(|t| CommentWithChildren::remove(t, id));
+        let key = Self::draft_key(cx, post_id, reply.parent_id);
+        Drafts::save(key.clone(), reply.content);
+        self.editing.set(Some(key));
+    }
+}
+
 #[component]
 pub fn ReplyBox(
     cx: Scope,
@@ -39,41 +294,159 @@ pub fn ReplyBox(
     parent_id: Option<CommentId>,
     reply: ReadSignal<bool>,
     set_reply: WriteSignal<bool>,
-    children: RwSignal<Vec<CommentWithChildren>>,
+    /// the whole thread, replies go in under `parent_id`
+    thread: RwSignal<Vec<CommentWithChildren>>,
+    /// Text to quote into the reply, set by the "quote" button on comments
+    #[prop(optional)]
+    quote: Option<ReadSignal<Option<String>>>,
 ) -> impl IntoView {
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    let pending = use_context::<PendingReplies>(cx).unwrap();
     let (content, set_content) = create_signal(cx, "".to_string());
+    let draft_key = create_memo(cx, move |_| {
+        DraftKey::new(
+            current_user().as_ref(),
+            DraftTarget::Reply { post_id, parent_id },
+        )
+    });
+    // an unsent draft from earlier that we offer to restore
+    let (saved_draft, set_saved_draft) = create_signal(cx, None::<Draft>);
+    create_effect(cx, move |_| {
+        let draft = Drafts::get(&draft_key());
+        if reply.get_untracked() {
+            // still open from before its comment was scrolled away, carry on writing
+            set_content(draft.map(|d| d.content).unwrap_or_default());
+            set_saved_draft(None);
+        } else {
+            set_content("".to_string());
+            set_saved_draft(draft);
+        }
+    });
+    // a reply that failed to send, taken back to edit
+    create_effect(cx, move |_| {
+        let key = draft_key();
+        if pending.editing.with(|e| e.as_ref() == Some(&key)) {
+            pending.editing.set(None);
+            set_content(Drafts::get(&key).map(|d| d.content).unwrap_or_default());
+            set_saved_draft(None);
+            set_reply(true);
+        }
+    });
+    let on_input = move |value: String| {
+        Drafts::save(draft_key.get_untracked(), value.clone());
+        set_content(value);
+    };
+    if let Some(quote) = quote {
+        create_effect(cx, move |_| {
+            if let Some(quote) = quote() {
+                let mut text = content.get_untracked();
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&quote_markdown(&quote));
+                on_input(text);
+                set_reply(true);
+            }
+        });
+    }
+    let send = move || {
+        let content = content.get_untracked();
+        set_content("".to_string());
+        set_reply(false);
+        pending.submit(cx, thread, parent_id, content);
+    };
     view! { cx,
         <div class="flex flex-col" class:hidden=move || !reply()>
-            <textarea
-                class="h-36 w-[calc(100%-30px)] rounded ring inset-2 ring-neutral-700 focus:ring-neutral-500 bg-neutral-700 text-neutral-100 p-4 m-4"
-                on:input=move |i| { set_content(event_target_value(&i)) }
-            ></textarea>
-            <div class="flex flex-row">
-                <button
-                    class="bg-gray-600 p-1 rounded hover:bg-gray-300"
-                    on:click=move |_| {
-                        let request = CreateComment {
-                            content: content(),
-                            post_id,
-                            parent_id,
-                            ..Default::default()
-                        };
-                        set_content("".to_string());
-                        set_reply(false);
-                        spawn_local(async move {
-                            let client = use_context::<CapyClient>(cx).unwrap();
-                            if let Ok(response) = client.execute(request).await {
-                                children
-                                    .update(|c| {
-                                        c.push(CommentWithChildren(response.comment_view, vec![]));
-                                    });
-                            }
-                        });
-                    }
-                >
-                    "send reply"
-                </button>
-            </div>
+            {move || saved_draft().map(|draft| view! { cx,
+                <div class="flex flex-row gap-2 p-1 mx-4 bg-neutral-800 rounded">
+                    "You have an unsent draft from "
+                    <RelativeTime time=draft.updated/>
+                    " ago"
+                    <button
+                        class="bg-gray-600 px-1 rounded hover:bg-gray-300"
+                        on:click=move |_| {
+                            set_content(draft.content.clone());
+                            set_saved_draft(None);
+                        }
+                    >
+                        "restore"
+                    </button>
+                    <button
+                        class="bg-gray-600 px-1 rounded hover:bg-gray-300"
+                        on:click=move |_| {
+                            Drafts::remove(&draft_key.get_untracked());
+                            set_saved_draft(None);
+                        }
+                    >
+                        "discard"
+                    </button>
+                </div>
+            })}
+            <MarkdownEditor
+                content
+                on_input
+                on_submit=send
+                disabled=Signal::derive(cx, move || saved_draft.with(|d| d.is_some()))
+                max_length=COMMENT_MAX_LENGTH
+                submit_text="send reply"
+            />
         </div>
     }
 }
+
+/// A reply in the thread that the server doesn't have yet
+#[component]
+pub fn PendingReplyView(
+    cx: Scope,
+    id: CommentId,
+    thread: RwSignal<Vec<CommentWithChildren>>,
+) -> impl IntoView {
+    let pending = use_context::<PendingReplies>(cx).unwrap();
+    let reply = create_memo(cx, move |_| pending.replies.with(|r| r.get(&id).cloned()));
+    // placeholders saved with a cached thread have nothing behind them
+    move || {
+        reply().map(|reply| {
+            let PendingReply {
+                content,
+                error,
+                queued,
+                ..
+            } = reply;
+            let failed = error.is_some();
+            view! { cx,
+                <div
+                    class="flex flex-col border-neutral-700 border-solid border-t-2 p-1"
+                    class:animate-pulse=!failed && !queued
+                >
+                    <div class="text-gray-500">
+                        {if failed {
+                            "failed to send"
+                        } else if queued {
+                            "offline, will be sent once you're back online"
+                        } else {
+                            "sending..."
+                        }}
+                    </div>
+                    <div class="whitespace-pre-wrap">{content}</div>
+                    {error.map(|error| view! { cx,
+                        <div class="text-red-600">{error}</div>
+                        <div class="flex flex-row gap-2">
+                            <button
+                                class="bg-gray-600 px-1 rounded hover:bg-gray-300"
+                                on:click=move |_| pending.send(cx, thread, id)
+                            >
+                                "retry"
+                            </button>
+                            <button
+                                class="bg-gray-600 px-1 rounded hover:bg-gray-300"
+                                on:click=move |_| pending.edit(cx, thread, id)
+                            >
+                                "edit"
+                            </button>
+                        </div>
+                    })}
+                </div>
+            }
+        })
+    }
+}
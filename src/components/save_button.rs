@@ -2,11 +2,10 @@ use leptos::*;
 use leptos_icons::{BiIcon, Icon};
 
 #[component]
-pub fn SaveButton(
-    cx: Scope,
-    saved: ReadSignal<bool>,
-    set_saved: WriteSignal<bool>,
-) -> impl IntoView {
+pub fn SaveButton<F>(cx: Scope, #[prop(into)] saved: Signal<bool>, on_save: F) -> impl IntoView
+where
+    F: Fn(bool) + 'static,
+{
     view! { cx,
         <button
             class=move || {
@@ -16,7 +15,7 @@ pub fn SaveButton(
                     "flex flex-row bold text-gray-500 hover:text-gray-400 align-center leading-none"
                 }
             }
-            on:click=move |_| { set_saved(!saved()) }
+            on:click=move |_| { on_save(!saved()) }
         >
             <Icon icon=MaybeSignal::Static(BiIcon::BiSaveRegular.into())/>
             {move || if saved() { "saved" } else { "save" }}
@@ -0,0 +1,78 @@
+use capybara_lemmy_client::CapyClient;
+use leptos::*;
+use leptos_icons::{BsIcon, Icon};
+use url::Url;
+use wasm_bindgen_futures::JsFuture;
+
+use super::toast::Toasts;
+
+/// Copies `text`, with a toast saying whether it worked
+pub fn copy_to_clipboard(cx: Scope, text: String, what: &'static str) {
+    let toasts = use_context::<Toasts>(cx).unwrap();
+    let Some(clipboard) = window().navigator().clipboard() else {
+        toasts.error(format!("couldn't copy the {what}, there's no clipboard"));
+        return;
+    };
+    let promise = clipboard.write_text(&text);
+    spawn_local(async move {
+        match JsFuture::from(promise).await {
+            Ok(_) => toasts.info(format!("copied the {what}")),
+            Err(e) => toasts.error(format!("couldn't copy the {what}: {e:?}")),
+        }
+    });
+}
+
+/// The link to an object on the instance the app is using
+pub fn instance_link(cx: Scope, path: &str) -> String {
+    let instance = use_context::<CapyClient>(cx).unwrap().get_instance();
+    format!("{instance}{path}")
+}
+
+/// A link that opens wherever the person it's shared with has an account, the app resolves these
+/// through their own instance
+pub fn app_link(ap_id: &Url) -> String {
+    format!(
+        "lemmy://{}{}",
+        ap_id.host_str().unwrap_or_default(),
+        ap_id.path()
+    )
+}
+
+/// A share button with a menu of things to copy, `targets` are labels and what they copy
+#[component]
+pub fn ShareMenu<F>(cx: Scope, targets: F) -> impl IntoView
+where
+    F: Fn() -> Vec<(&'static str, String)> + 'static,
+{
+    let (open, set_open) = create_signal(cx, false);
+    view! { cx,
+        <div class="relative">
+            <button
+                class="text-gray-500 hover:text-gray-400 flex flex-row gap-1 underline"
+                on:click=move |_| set_open(!open())
+            >
+                <Icon icon=MaybeSignal::Static(BsIcon::BsShareFill.into())/>
+                "share"
+            </button>
+            {move || open().then(|| view! { cx,
+                <div class="flex flex-col absolute top-5 left-0 z-30 bg-neutral-800 rounded p-1 w-max">
+                    {targets()
+                        .into_iter()
+                        .map(|(what, text)| view! { cx,
+                            <button
+                                class="hover:bg-neutral-600 px-1 rounded text-left"
+                                title=text.clone()
+                                on:click=move |_| {
+                                    copy_to_clipboard(cx, text.clone(), what);
+                                    set_open(false);
+                                }
+                            >
+                                {what}
+                            </button>
+                        })
+                        .collect::<Vec<_>>()}
+                </div>
+            })}
+        </div>
+    }
+}
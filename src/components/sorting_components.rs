@@ -41,7 +41,7 @@ fn comment_sort_to_text(sort_type: Option<CommentSortType>) -> &'static str {
 #[component]
 pub fn SortMenu(
     cx: Scope,
-    sort: ReadSignal<Option<SortType>>,
+    #[prop(into)] sort: Signal<Option<SortType>>,
     set_sort: WriteSignal<Option<SortType>>,
 ) -> impl IntoView {
     let sort_menu_hidden = create_rw_signal(cx, true);
@@ -140,7 +140,7 @@ pub fn CommentSortMenu(
 #[component]
 pub fn TypeMenu(
     cx: Scope,
-    type_: ReadSignal<Option<ListingType>>,
+    #[prop(into)] type_: Signal<Option<ListingType>>,
     set_type: WriteSignal<Option<ListingType>>,
 ) -> impl IntoView {
     let options = [
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use leptos::*;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+#[derive(Clone)]
+pub struct Toast {
+    id: usize,
+    pub kind: ToastKind,
+    pub message: String,
+}
+
+/// Short lived notifications shown in the corner of the screen
+#[derive(Clone, Copy)]
+pub struct Toasts {
+    toasts: RwSignal<Vec<Toast>>,
+    next_id: StoredValue<usize>,
+}
+
+impl Toasts {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            toasts: create_rw_signal(cx, vec![]),
+            next_id: store_value(cx, 0),
+        }
+    }
+
+    pub fn push(&self, kind: ToastKind, message: impl ToString) {
+        let id = self.next_id.get_value();
+        self.next_id.set_value(id + 1);
+        self.toasts.update(|t| {
+            t.push(Toast {
+                id,
+                kind,
+                message: message.to_string(),
+            })
+        });
+        let toasts = *self;
+        set_timeout(move || toasts.dismiss(id), Duration::from_secs(5));
+    }
+
+    pub fn info(&self, message: impl ToString) {
+        self.push(ToastKind::Info, message);
+    }
+
+    pub fn error(&self, message: impl ToString) {
+        self.push(ToastKind::Error, message);
+    }
+
+    fn dismiss(&self, id: usize) {
+        self.toasts.update(|t| t.retain(|t| t.id != id));
+    }
+}
+
+#[component]
+pub fn ToastList(cx: Scope) -> impl IntoView {
+    let toasts = use_context::<Toasts>(cx).unwrap();
+    view! { cx,
+        <div class="flex flex-col gap-2 fixed bottom-10 left-10 z-50">
+            <For
+                each=toasts.toasts
+                key=|t| t.id
+                view=move |cx, toast: Toast| {
+                    let Toast { id, kind, message } = toast;
+                    view! { cx,
+                        <button
+                            class="p-2 rounded text-left"
+                            class:bg-red-700=kind == ToastKind::Error
+                            class:bg-neutral-700=kind == ToastKind::Info
+                            on:click=move |_| toasts.dismiss(id)
+                        >
+                            {message}
+                        </button>
+                    }
+                }
+            />
+        </div>
+    }
+}
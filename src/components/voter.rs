@@ -1,31 +1,32 @@
-use crate::app::CurrentUser;
 use leptos::*;
 use leptos_icons::{BiIcon, Icon};
 
+use crate::account::Account;
+
 #[component]
-pub fn Voter(
+pub fn Voter<F>(
     cx: Scope,
-    my_vote: RwSignal<Option<i16>>,
-    upvotes: i64,
-    downvotes: i64,
-    score: i64,
-) -> impl IntoView {
-    let clean_score = score - my_vote.get_untracked().unwrap_or_default() as i64;
-    let clean_downvotes = downvotes - (my_vote.get_untracked().unwrap_or_default() == -1) as i64;
-    let clean_upvotes = upvotes - (my_vote.get_untracked().unwrap_or_default() == 1) as i64;
-    let upvotes = move || clean_upvotes + (my_vote().unwrap_or_default() == 1) as i64;
-    let downvotes = move || clean_downvotes + (my_vote().unwrap_or_default() == -1) as i64;
-    let score = move || clean_score + my_vote().unwrap_or_default() as i64;
-
+    #[prop(into)] my_vote: Signal<Option<i16>>,
+    #[prop(into)] upvotes: Signal<i64>,
+    #[prop(into)] downvotes: Signal<i64>,
+    #[prop(into)] score: Signal<i64>,
+    /// Called with the new vote when one of the buttons is clicked
+    on_vote: F,
+) -> impl IntoView
+where
+    F: Fn(Option<i16>) + Copy + 'static,
+{
+    // the account's server setting, the buttons stay either way
+    let show_scores = move || use_context::<Account>(cx).unwrap().show_scores();
     view! { cx,
         <div class="flex flex-col h-fit pr-2">
             <button
                 class="flex flex-row text-red-400 hover:text-red-600 align-text-top leading-none"
                 on:click=move |_| {
                     if my_vote().unwrap_or_default() == 1 {
-                        my_vote.set(None);
+                        on_vote(None);
                     } else {
-                        my_vote.set(Some(1))
+                        on_vote(Some(1))
                     }
                 }
             >
@@ -36,16 +37,16 @@ pub fn Voter(
                         view! { cx, <Icon icon=MaybeSignal::Static(BiIcon::BiUpvoteRegular.into())/> }
                     }
                 }}
-                {move || upvotes()}
+                {move || show_scores().then(upvotes)}
             </button>
-            <div class="text-gray-500">{move || score()}</div>
+            <div class="text-gray-500">{move || show_scores().then(score)}</div>
             <button
                 class="flex flex-row text-blue-300 hover:text-blue-600 align-text-top leading-none"
                 on:click=move |_| {
                     if my_vote().unwrap_or_default() == -1 {
-                        my_vote.set(None);
+                        on_vote(None);
                     } else {
-                        my_vote.set(Some(-1))
+                        on_vote(Some(-1))
                     }
                 }
             >
@@ -56,7 +57,7 @@ pub fn Voter(
                         view! { cx, <Icon icon=MaybeSignal::Static(BiIcon::BiDownvoteRegular.into())/> }
                     }
                 }}
-                {move || downvotes()}
+                {move || show_scores().then(downvotes)}
             </button>
         </div>
     }
@@ -0,0 +1,132 @@
+use capybara_lemmy_client::{comment::CommentId, post::PostId};
+use chrono::{NaiveDateTime, Utc};
+use gloo::storage::{LocalStorage, Storage};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{app::CurrentUser, components::time::RelativeTime, settings::LoginInfo};
+
+/// What an unfinished piece of text was going to be sent as. Only replies so far, there's no
+/// way to write a post in the app yet.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum DraftTarget {
+    Reply {
+        post_id: PostId,
+        parent_id: Option<CommentId>,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct DraftKey {
+    /// username@instance of the account the draft was written on, None for guests
+    pub account: Option<String>,
+    pub target: DraftTarget,
+}
+
+impl DraftKey {
+    pub fn new(login: Option<&LoginInfo>, target: DraftTarget) -> Self {
+        Self {
+            account: Self::account(login),
+            target,
+        }
+    }
+
+    fn account(login: Option<&LoginInfo>) -> Option<String> {
+        login.map(|l| format!("{}@{}", l.username, l.instance))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Draft {
+    pub key: DraftKey,
+    pub content: String,
+    pub updated: NaiveDateTime,
+}
+
+/// Autosaved replies, kept in local storage so they survive navigation and reloads
+pub struct Drafts;
+
+impl Drafts {
+    pub fn all() -> Vec<Draft> {
+        LocalStorage::get("drafts").ok().unwrap_or_default()
+    }
+
+    pub fn get(key: &DraftKey) -> Option<Draft> {
+        Self::all().into_iter().find(|d| &d.key == key)
+    }
+
+    /// Saves the draft, or removes it if there's nothing left worth keeping
+    pub fn save(key: DraftKey, content: String) {
+        let mut drafts = Self::all();
+        drafts.retain(|d| d.key != key);
+        if !content.trim().is_empty() {
+            drafts.insert(
+                0,
+                Draft {
+                    key,
+                    content,
+                    updated: Utc::now().naive_utc(),
+                },
+            );
+        }
+        LocalStorage::set("drafts", drafts).unwrap();
+    }
+
+    pub fn remove(key: &DraftKey) {
+        let mut drafts = Self::all();
+        drafts.retain(|d| &d.key != key);
+        LocalStorage::set("drafts", drafts).unwrap();
+    }
+}
+
+#[component]
+pub fn DraftList(cx: Scope) -> impl IntoView {
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    let drafts = create_rw_signal(cx, Drafts::all());
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"Drafts"</div>
+            {move || drafts.with(|d| d.is_empty()).then(|| view! { cx, "No unfinished drafts" })}
+            {move || {
+                let account = DraftKey::account(current_user().as_ref());
+                drafts()
+                    .into_iter()
+                    .map(|draft| {
+                        let Draft { key, content, updated } = draft;
+                        let DraftTarget::Reply { post_id, parent_id } = key.target;
+                        let link = format!("/post/{}", post_id.0);
+                        let kind = match parent_id {
+                            Some(_) => "reply to comment",
+                            None => "reply to post",
+                        };
+                        let other_account = key.account != account;
+                        let account_label = key.account.clone();
+                        view! { cx,
+                            <div class="flex flex-col bg-neutral-800 p-2 rounded">
+                                <div class="flex flex-row gap-2 text-gray-500">
+                                    {kind}
+                                    <RelativeTime time=updated/>
+                                    {account_label.map(|a| view! { cx, <div>"as " {a}</div> })}
+                                    {other_account.then(|| view! { cx, <div class="text-yellow-500">"(other account)"</div> })}
+                                </div>
+                                <div class="whitespace-pre-wrap line-clamp-3">{content}</div>
+                                <div class="flex flex-row gap-2">
+                                    <a class="underline text-red-400 hover:text-red-600" href=link>"continue"</a>
+                                    <button
+                                        class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                                        on:click=move |_| {
+                                            Drafts::remove(&key);
+                                            drafts.set(Drafts::all());
+                                        }
+                                    >
+                                        "discard"
+                                    </button>
+                                </div>
+                            </div>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,608 @@
+use capybara_lemmy_client::{
+    comment::CommentView, community::Community, person::Person, post::PostView,
+};
+use gloo::storage::{LocalStorage, Storage};
+use leptos::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// What a filter matches on
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum FilterRule {
+    /// case insensitive text in a post's title and body or a comment
+    Keyword(String),
+    /// like `Keyword`, as a regular expression
+    Regex(String),
+    /// the domain a post links to, including its subdomains
+    Domain(String),
+    /// `name` or `name@instance`
+    Community(String),
+    /// where the creator or community is from
+    Instance(String),
+    /// `name` or `name@instance`
+    User(String),
+    Nsfw,
+    Bots,
+    /// anything scored below this
+    MinScore(i64),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterAction {
+    /// drop it from the feed entirely
+    Hide,
+    /// show a placeholder that can be expanded
+    Collapse,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub rule: FilterRule,
+    pub action: FilterAction,
+}
+
+impl FilterRule {
+    pub fn describe(&self) -> String {
+        match self {
+            FilterRule::Keyword(k) => format!("keyword \"{k}\""),
+            FilterRule::Regex(r) => format!("regex /{r}/"),
+            FilterRule::Domain(d) => format!("domain {d}"),
+            FilterRule::Community(c) => format!("community {c}"),
+            FilterRule::Instance(i) => format!("instance {i}"),
+            FilterRule::User(u) => format!("user {u}"),
+            FilterRule::Nsfw => "NSFW".to_string(),
+            FilterRule::Bots => "bot account".to_string(),
+            FilterRule::MinScore(s) => format!("score below {s}"),
+        }
+    }
+}
+
+fn compile_regex(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+fn host(url: &Url) -> &str {
+    url.host_str().unwrap_or_default()
+}
+
+/// `name` matches on its own, `name@instance` only for that instance
+fn matches_actor(pattern: &str, name: &str, actor_id: &Url) -> bool {
+    match pattern.split_once('@') {
+        Some((pattern_name, instance)) => {
+            pattern_name.eq_ignore_ascii_case(name) && instance.eq_ignore_ascii_case(host(actor_id))
+        }
+        None => pattern.eq_ignore_ascii_case(name),
+    }
+}
+
+/// The parts of a post or comment that filters look at
+struct Target<'a> {
+    text: Vec<&'a str>,
+    url: Option<&'a Url>,
+    community: &'a Community,
+    creator: &'a Person,
+    nsfw: bool,
+    score: i64,
+}
+
+/// Filters with their regexes compiled, ready to be matched against posts and comments
+#[derive(Clone, Default)]
+pub struct CompiledFilters {
+    filters: Vec<Filter>,
+    regexes: Vec<Option<Regex>>,
+}
+
+impl PartialEq for CompiledFilters {
+    fn eq(&self, other: &Self) -> bool {
+        self.filters == other.filters
+    }
+}
+
+impl CompiledFilters {
+    pub fn new(filters: Vec<Filter>) -> Self {
+        let regexes = filters
+            .iter()
+            .map(|f| match &f.rule {
+                FilterRule::Regex(r) => compile_regex(r).ok(),
+                _ => None,
+            })
+            .collect();
+        Self { filters, regexes }
+    }
+
+    fn matches(&self, index: usize, target: &Target) -> bool {
+        match &self.filters[index].rule {
+            FilterRule::Keyword(k) => {
+                let k = k.to_lowercase();
+                target.text.iter().any(|t| t.to_lowercase().contains(&k))
+            }
+            FilterRule::Regex(_) => self.regexes[index]
+                .as_ref()
+                .is_some_and(|r| target.text.iter().any(|t| r.is_match(t))),
+            FilterRule::Domain(d) => target.url.is_some_and(|url| {
+                let host = host(url);
+                host.eq_ignore_ascii_case(d) || host.ends_with(&format!(".{d}"))
+            }),
+            FilterRule::Community(c) => {
+                matches_actor(c, &target.community.name, &target.community.actor_id)
+            }
+            FilterRule::Instance(i) => {
+                host(&target.creator.actor_id).eq_ignore_ascii_case(i)
+                    || host(&target.community.actor_id).eq_ignore_ascii_case(i)
+            }
+            FilterRule::User(u) => matches_actor(u, &target.creator.name, &target.creator.actor_id),
+            FilterRule::Nsfw => target.nsfw,
+            FilterRule::Bots => target.creator.bot_account,
+            FilterRule::MinScore(s) => target.score < *s,
+        }
+    }
+
+    /// The filter to apply, hiding wins over collapsing
+    fn find(&self, target: Target) -> Option<&Filter> {
+        let mut matching = (0..self.filters.len())
+            .filter(|i| self.matches(*i, &target))
+            .map(|i| &self.filters[i]);
+        let first = matching.next()?;
+        if first.action == FilterAction::Hide {
+            return Some(first);
+        }
+        Some(
+            matching
+                .find(|f| f.action == FilterAction::Hide)
+                .unwrap_or(first),
+        )
+    }
+
+    pub fn post(&self, post: &PostView) -> Option<&Filter> {
+        self.find(Target {
+            text: [Some(post.post.name.as_str()), post.post.body.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect(),
+            url: post.post.url.as_ref(),
+            community: &post.community,
+            creator: &post.creator,
+            nsfw: post.post.nsfw || post.community.nsfw,
+            score: post.counts.score,
+        })
+    }
+
+    pub fn comment(&self, comment: &CommentView) -> Option<&Filter> {
+        self.find(Target {
+            text: vec![comment.comment.content.as_str()],
+            url: None,
+            community: &comment.community,
+            creator: &comment.creator,
+            nsfw: comment.post.nsfw || comment.community.nsfw,
+            score: comment.counts.score,
+        })
+    }
+}
+
+/// The user's filters, kept in local storage
+#[derive(Clone, Copy)]
+pub struct ContentFilters {
+    pub filters: RwSignal<Vec<Filter>>,
+    compiled: Memo<CompiledFilters>,
+}
+
+impl ContentFilters {
+    pub fn new(cx: Scope) -> Self {
+        let filters = create_rw_signal(
+            cx,
+            LocalStorage::get::<Vec<Filter>>("filters").unwrap_or_default(),
+        );
+        create_effect(cx, move |_| {
+            filters.with(|f| LocalStorage::set("filters", f).unwrap());
+        });
+        Self {
+            filters,
+            compiled: create_memo(cx, move |_| CompiledFilters::new(filters())),
+        }
+    }
+
+    /// The filter that applies to `post`, tracked so callers re-run when the filters change
+    pub fn post(&self, post: &PostView) -> Option<Filter> {
+        self.compiled.with(|c| c.post(post).cloned())
+    }
+
+    pub fn comment(&self, comment: &CommentView) -> Option<Filter> {
+        self.compiled.with(|c| c.comment(comment).cloned())
+    }
+
+    pub fn hides_post(&self, post: &PostView) -> bool {
+        self.post(post)
+            .is_some_and(|f| f.action == FilterAction::Hide)
+    }
+
+    pub fn hides_comment(&self, comment: &CommentView) -> bool {
+        self.comment(comment)
+            .is_some_and(|f| f.action == FilterAction::Hide)
+    }
+}
+
+/// Stands in for something a filter collapsed, with a way to show it anyway
+#[component]
+pub fn FilteredNotice(cx: Scope, filter: Filter, children: ChildrenFn) -> impl IntoView {
+    let (shown, set_shown) = create_signal(cx, false);
+    view! { cx,
+        <div class="flex flex-row gap-2 p-1 text-gray-500 bg-neutral-900 border-neutral-500 border-b-4">
+            "filtered: "
+            {filter.rule.describe()}
+            <button class="underline hover:text-gray-300" on:click=move |_| set_shown(!shown())>
+                {move || if shown() { "hide" } else { "show" }}
+            </button>
+        </div>
+        {move || shown().then(|| children(cx))}
+    }
+}
+
+const KINDS: [&str; 9] = [
+    "keyword",
+    "regex",
+    "domain",
+    "community",
+    "instance",
+    "user",
+    "nsfw",
+    "bots",
+    "min score",
+];
+
+fn parse_rule(kind: &str, value: &str) -> Result<FilterRule, String> {
+    let value = value.trim().to_string();
+    let needs_value = !matches!(kind, "nsfw" | "bots");
+    if needs_value && value.is_empty() {
+        return Err("needs a value".to_string());
+    }
+    Ok(match kind {
+        "keyword" => FilterRule::Keyword(value),
+        "regex" => {
+            compile_regex(&value).map_err(|e| e.to_string())?;
+            FilterRule::Regex(value)
+        }
+        "domain" => FilterRule::Domain(value.trim_start_matches("www.").to_string()),
+        "community" => FilterRule::Community(value.trim_start_matches('!').to_string()),
+        "instance" => FilterRule::Instance(value),
+        "user" => FilterRule::User(value.trim_start_matches('@').to_string()),
+        "nsfw" => FilterRule::Nsfw,
+        "bots" => FilterRule::Bots,
+        "min score" => FilterRule::MinScore(value.parse().map_err(|_| "not a number")?),
+        _ => return Err(format!("unknown filter {kind}")),
+    })
+}
+
+#[component]
+pub fn FilterList(cx: Scope) -> impl IntoView {
+    let content_filters = use_context::<ContentFilters>(cx).unwrap();
+    let filters = content_filters.filters;
+    let (kind, set_kind) = create_signal(cx, KINDS[0].to_string());
+    let (value, set_value) = create_signal(cx, String::new());
+    let (action, set_action) = create_signal(cx, FilterAction::Hide);
+    let (error, set_error) = create_signal(cx, None::<String>);
+    let (transfer, set_transfer) = create_signal(cx, String::new());
+    let add = move || match parse_rule(&kind.get_untracked(), &value.get_untracked()) {
+        Ok(rule) => {
+            let filter = Filter {
+                rule,
+                action: action.get_untracked(),
+            };
+            filters.update(|f| {
+                if !f.contains(&filter) {
+                    f.push(filter);
+                }
+            });
+            set_value(String::new());
+            set_error(None);
+        }
+        Err(e) => set_error(Some(e)),
+    };
+    let import = move || match serde_json::from_str::<Vec<Filter>>(&transfer.get_untracked()) {
+        Ok(imported) => {
+            filters.update(|f| {
+                for filter in imported {
+                    if !f.contains(&filter) {
+                        f.push(filter);
+                    }
+                }
+            });
+            set_transfer(String::new());
+            set_error(None);
+        }
+        Err(e) => set_error(Some(format!("couldn't import filters: {e}"))),
+    };
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"Filters"</div>
+            <div class="flex flex-row gap-2">
+                <select
+                    class="bg-neutral-800 rounded"
+                    on:change=move |e| set_kind(event_target_value(&e))
+                >
+                    {KINDS
+                        .into_iter()
+                        .map(|k| view! { cx, <option value=k selected=move || kind() == k>{k}</option> })
+                        .collect::<Vec<_>>()}
+                </select>
+                <input
+                    class="bg-neutral-800 rounded px-1 grow"
+                    class:hidden=move || matches!(kind().as_str(), "nsfw" | "bots")
+                    prop:value=value
+                    on:input=move |e| set_value(event_target_value(&e))
+                    on:keydown=move |e| {
+                        if e.key() == "Enter" {
+                            add();
+                        }
+                    }
+                />
+                <select
+                    class="bg-neutral-800 rounded"
+                    on:change=move |e| {
+                        set_action(if event_target_value(&e) == "collapse" {
+                            FilterAction::Collapse
+                        } else {
+                            FilterAction::Hide
+                        })
+                    }
+                >
+                    <option value="hide" selected=move || action() == FilterAction::Hide>"hide"</option>
+                    <option value="collapse" selected=move || action() == FilterAction::Collapse>"collapse"</option>
+                </select>
+                <button class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded" on:click=move |_| add()>
+                    "add"
+                </button>
+            </div>
+            {move || error().map(|e| view! { cx, <div class="text-red-600">{e}</div> })}
+            {move || filters.with(|f| f.is_empty()).then(|| view! { cx, "No filters yet" })}
+            {move || {
+                filters()
+                    .into_iter()
+                    .map(|filter| {
+                        let description = filter.rule.describe();
+                        let action = match filter.action {
+                            FilterAction::Hide => "hide",
+                            FilterAction::Collapse => "collapse",
+                        };
+                        view! { cx,
+                            <div class="flex flex-row gap-2 bg-neutral-800 p-2 rounded">
+                                <div class="grow">{description}</div>
+                                <div class="text-gray-500">{action}</div>
+                                <button
+                                    class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                                    on:click=move |_| filters.update(|f| f.retain(|f| f != &filter))
+                                >
+                                    "remove"
+                                </button>
+                            </div>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+            <div class="text-xl">"Import / export"</div>
+            <textarea
+                class="bg-neutral-800 rounded p-1 h-40 font-mono"
+                placeholder="paste exported filters here"
+                prop:value=transfer
+                on:input=move |e| set_transfer(event_target_value(&e))
+            ></textarea>
+            <div class="flex flex-row gap-2">
+                <button
+                    class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                    on:click=move |_| {
+                        set_transfer(serde_json::to_string_pretty(&filters.get_untracked()).unwrap_or_default());
+                    }
+                >
+                    "export"
+                </button>
+                <button class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded" on:click=move |_| import()>
+                    "import"
+                </button>
+            </div>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person(name: &str, instance: &str, bot: bool) -> Person {
+        serde_json::from_value(json!({
+            "id": 1,
+            "name": name,
+            "banned": false,
+            "published": "2023-07-01T00:00:00",
+            "actor_id": format!("https://{instance}/u/{name}"),
+            "local": false,
+            "deleted": false,
+            "admin": false,
+            "bot_account": bot,
+            "instance_id": 1,
+        }))
+        .unwrap()
+    }
+
+    fn community(name: &str, instance: &str) -> Community {
+        serde_json::from_value(json!({
+            "id": 1,
+            "name": name,
+            "title": name,
+            "removed": false,
+            "published": "2023-07-01T00:00:00",
+            "deleted": false,
+            "nsfw": false,
+            "actor_id": format!("https://{instance}/c/{name}"),
+            "local": false,
+            "hidden": false,
+            "posting_restricted_to_mods": false,
+            "instance_id": 1,
+        }))
+        .unwrap()
+    }
+
+    struct Fixture {
+        url: Option<Url>,
+        community: Community,
+        creator: Person,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self {
+                url: Some(Url::parse("https://blog.example.com/post").unwrap()),
+                community: community("rust", "lemmy.ml"),
+                creator: person("ferris", "programming.dev", false),
+            }
+        }
+
+        fn target(&self) -> Target<'_> {
+            Target {
+                text: vec!["Rust 1.71 is out", "with a new Release"],
+                url: self.url.as_ref(),
+                community: &self.community,
+                creator: &self.creator,
+                nsfw: false,
+                score: 5,
+            }
+        }
+    }
+
+    fn matches(rule: FilterRule, target: &Target) -> bool {
+        let filters = CompiledFilters::new(vec![Filter {
+            rule,
+            action: FilterAction::Hide,
+        }]);
+        filters.matches(0, target)
+    }
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(
+            parse_rule("keyword", " rust "),
+            Ok(FilterRule::Keyword("rust".to_string()))
+        );
+        assert_eq!(
+            parse_rule("domain", "www.example.com"),
+            Ok(FilterRule::Domain("example.com".to_string()))
+        );
+        assert_eq!(
+            parse_rule("community", "!rust@lemmy.ml"),
+            Ok(FilterRule::Community("rust@lemmy.ml".to_string()))
+        );
+        assert_eq!(
+            parse_rule("user", "@ferris"),
+            Ok(FilterRule::User("ferris".to_string()))
+        );
+        assert_eq!(parse_rule("nsfw", ""), Ok(FilterRule::Nsfw));
+        assert_eq!(parse_rule("min score", "-3"), Ok(FilterRule::MinScore(-3)));
+        assert!(parse_rule("min score", "lots").is_err());
+        assert!(parse_rule("keyword", "  ").is_err());
+        assert!(parse_rule("regex", "(unclosed").is_err());
+        assert!(parse_rule("colour", "red").is_err());
+    }
+
+    #[test]
+    fn matches_text() {
+        let fixture = Fixture::new();
+        let target = fixture.target();
+        assert!(matches(FilterRule::Keyword("RELEASE".to_string()), &target));
+        assert!(!matches(FilterRule::Keyword("golang".to_string()), &target));
+        assert!(matches(
+            FilterRule::Regex(r"rust \d+\.\d+".to_string()),
+            &target
+        ));
+        assert!(!matches(
+            FilterRule::Regex(r"^release".to_string()),
+            &target
+        ));
+        // a pattern that didn't compile never matches
+        assert!(!matches(FilterRule::Regex("(".to_string()), &target));
+    }
+
+    #[test]
+    fn matches_domains() {
+        let mut fixture = Fixture::new();
+        assert!(matches(
+            FilterRule::Domain("example.com".to_string()),
+            &fixture.target()
+        ));
+        assert!(matches(
+            FilterRule::Domain("blog.example.com".to_string()),
+            &fixture.target()
+        ));
+        assert!(!matches(
+            FilterRule::Domain("ample.com".to_string()),
+            &fixture.target()
+        ));
+        fixture.url = None;
+        assert!(!matches(
+            FilterRule::Domain("example.com".to_string()),
+            &fixture.target()
+        ));
+    }
+
+    #[test]
+    fn matches_actors() {
+        let fixture = Fixture::new();
+        let target = fixture.target();
+        assert!(matches(FilterRule::Community("Rust".to_string()), &target));
+        assert!(matches(
+            FilterRule::Community("rust@lemmy.ml".to_string()),
+            &target
+        ));
+        assert!(!matches(
+            FilterRule::Community("rust@beehaw.org".to_string()),
+            &target
+        ));
+        assert!(matches(
+            FilterRule::User("ferris@programming.dev".to_string()),
+            &target
+        ));
+        assert!(!matches(FilterRule::User("crab".to_string()), &target));
+        assert!(matches(
+            FilterRule::Instance("lemmy.ml".to_string()),
+            &target
+        ));
+        assert!(matches(
+            FilterRule::Instance("programming.dev".to_string()),
+            &target
+        ));
+        assert!(!matches(
+            FilterRule::Instance("beehaw.org".to_string()),
+            &target
+        ));
+    }
+
+    #[test]
+    fn matches_flags_and_scores() {
+        let mut fixture = Fixture::new();
+        assert!(!matches(FilterRule::Bots, &fixture.target()));
+        assert!(!matches(FilterRule::Nsfw, &fixture.target()));
+        assert!(matches(FilterRule::MinScore(10), &fixture.target()));
+        assert!(!matches(FilterRule::MinScore(5), &fixture.target()));
+        fixture.creator = person("ferris", "programming.dev", true);
+        assert!(matches(FilterRule::Bots, &fixture.target()));
+    }
+
+    #[test]
+    fn hiding_wins_over_collapsing() {
+        let fixture = Fixture::new();
+        let collapse = Filter {
+            rule: FilterRule::Keyword("rust".to_string()),
+            action: FilterAction::Collapse,
+        };
+        let hide = Filter {
+            rule: FilterRule::Community("rust".to_string()),
+            action: FilterAction::Hide,
+        };
+        let filters = CompiledFilters::new(vec![collapse.clone(), hide.clone()]);
+        assert_eq!(filters.find(fixture.target()), Some(&hide));
+        let filters = CompiledFilters::new(vec![collapse.clone()]);
+        assert_eq!(filters.find(fixture.target()), Some(&collapse));
+        assert_eq!(CompiledFilters::default().find(fixture.target()), None);
+    }
+}
@@ -1,8 +1,16 @@
+pub mod account;
 mod app;
+pub mod blocks;
 pub mod community;
 pub mod community_list;
 pub mod components;
+pub mod drafts;
+pub mod filters;
 pub mod login;
+pub mod mutation;
+pub mod nsfw;
+pub mod preferences;
+pub mod routing;
 pub mod settings;
 
 use app::*;
@@ -0,0 +1,357 @@
+use std::{collections::HashMap, hash::Hash};
+
+use capybara_lemmy_client::{
+    comment::{CommentId, CommentResponse, CommentView},
+    community::CommunityView,
+    error::ClientError,
+    post::{PostId, PostResponse, PostView},
+    CapyClient, LemmyRequest,
+};
+use leptos::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::components::{comments::CommentWithChildren, feed::cache::FeedCache, toast::Toasts};
+
+/// The parts of a post that change when the user interacts with it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PostState {
+    pub my_vote: Option<i16>,
+    pub score: i64,
+    pub upvotes: i64,
+    pub downvotes: i64,
+    pub saved: bool,
+    pub read: bool,
+}
+
+/// The parts of a comment that change when the user interacts with it
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CommentState {
+    pub my_vote: Option<i16>,
+    pub score: i64,
+    pub upvotes: i64,
+    pub downvotes: i64,
+    pub saved: bool,
+}
+
+/// Moves a vote from `old` to `new`, keeping the counts in step
+fn apply_vote(
+    old: Option<i16>,
+    new: Option<i16>,
+    score: &mut i64,
+    upvotes: &mut i64,
+    downvotes: &mut i64,
+) {
+    for (vote, sign) in [(old, -1), (new, 1)] {
+        match vote {
+            Some(1) => *upvotes += sign,
+            Some(-1) => *downvotes += sign,
+            _ => {}
+        }
+        *score += vote.unwrap_or_default() as i64 * sign;
+    }
+}
+
+impl PostState {
+    pub fn vote(&mut self, vote: Option<i16>) {
+        let old = self.my_vote;
+        apply_vote(
+            old,
+            vote,
+            &mut self.score,
+            &mut self.upvotes,
+            &mut self.downvotes,
+        );
+        self.my_vote = vote;
+    }
+
+    fn apply(&self, post: &mut PostView) {
+        post.my_vote = self.my_vote;
+        post.counts.score = self.score;
+        post.counts.upvotes = self.upvotes;
+        post.counts.downvotes = self.downvotes;
+        post.saved = self.saved;
+        post.read = self.read;
+    }
+}
+
+impl From<&PostView> for PostState {
+    fn from(post: &PostView) -> Self {
+        Self {
+            my_vote: post.my_vote,
+            score: post.counts.score,
+            upvotes: post.counts.upvotes,
+            downvotes: post.counts.downvotes,
+            saved: post.saved,
+            read: post.read,
+        }
+    }
+}
+
+impl CommentState {
+    pub fn vote(&mut self, vote: Option<i16>) {
+        let old = self.my_vote;
+        apply_vote(
+            old,
+            vote,
+            &mut self.score,
+            &mut self.upvotes,
+            &mut self.downvotes,
+        );
+        self.my_vote = vote;
+    }
+
+    fn apply(&self, comment: &mut CommentView) {
+        comment.my_vote = self.my_vote;
+        comment.counts.score = self.score;
+        comment.counts.upvotes = self.upvotes;
+        comment.counts.downvotes = self.downvotes;
+        comment.saved = self.saved;
+    }
+}
+
+impl From<&CommentView> for CommentState {
+    fn from(comment: &CommentView) -> Self {
+        Self {
+            my_vote: comment.my_vote,
+            score: comment.counts.score,
+            upvotes: comment.counts.upvotes,
+            downvotes: comment.counts.downvotes,
+            saved: comment.saved,
+        }
+    }
+}
+
+/// Orders the requests made for one item, so only the latest one's outcome is shown
+pub struct Sequence<S> {
+    /// the generation of the most recent request
+    latest: u64,
+    /// what the server last confirmed, failures roll back to this
+    confirmed: Option<S>,
+}
+
+/// Latest known state of every post and comment changed this session, so that every copy of
+/// an item on screen or in the feed caches shows the same thing.
+#[derive(Clone, Copy)]
+pub struct ItemStore {
+    posts: RwSignal<HashMap<PostId, PostState>>,
+    comments: RwSignal<HashMap<CommentId, CommentState>>,
+    post_sequences: StoredValue<HashMap<PostId, Sequence<PostState>>>,
+    comment_sequences: StoredValue<HashMap<CommentId, Sequence<CommentState>>>,
+}
+
+pub trait ItemState: Clone + PartialEq + 'static {
+    type Id: Copy + Eq + Hash + 'static;
+    type Response: DeserializeOwned + std::fmt::Debug;
+
+    fn items(store: &ItemStore) -> RwSignal<HashMap<Self::Id, Self>>;
+
+    fn sequences(store: &ItemStore) -> StoredValue<HashMap<Self::Id, Sequence<Self>>>;
+
+    fn from_response(response: Self::Response) -> Self;
+
+    /// Updates copies of the item that were saved away when their feed was closed
+    fn patch_cached_feeds(id: Self::Id, state: &Self);
+}
+
+impl ItemState for PostState {
+    type Id = PostId;
+    type Response = PostResponse;
+
+    fn items(store: &ItemStore) -> RwSignal<HashMap<PostId, PostState>> {
+        store.posts
+    }
+
+    fn sequences(store: &ItemStore) -> StoredValue<HashMap<PostId, Sequence<PostState>>> {
+        store.post_sequences
+    }
+
+    fn from_response(response: PostResponse) -> Self {
+        PostState::from(&response.post_view)
+    }
+
+    fn patch_cached_feeds(id: PostId, state: &Self) {
+        FeedCache::patch(|post: &mut PostView| {
+            let matches = post.post.id == id;
+            if matches {
+                state.apply(post);
+            }
+            matches
+        });
+    }
+}
+
+impl ItemState for CommentState {
+    type Id = CommentId;
+    type Response = CommentResponse;
+
+    fn items(store: &ItemStore) -> RwSignal<HashMap<CommentId, CommentState>> {
+        store.comments
+    }
+
+    fn sequences(store: &ItemStore) -> StoredValue<HashMap<CommentId, Sequence<CommentState>>> {
+        store.comment_sequences
+    }
+
+    fn from_response(response: CommentResponse) -> Self {
+        CommentState::from(&response.comment_view)
+    }
+
+    fn patch_cached_feeds(id: CommentId, state: &Self) {
+        fn patch(comment: &mut CommentWithChildren, id: CommentId, state: &CommentState) -> bool {
+            if comment.0.comment.id == id {
+                state.apply(&mut comment.0);
+                return true;
+            }
+            comment.1.iter_mut().any(|child| patch(child, id, state))
+        }
+        FeedCache::patch(|comment: &mut CommentWithChildren| patch(comment, id, state));
+    }
+}
+
+impl ItemStore {
+    pub fn new(cx: Scope) -> Self {
+        Self {
+            posts: create_rw_signal(cx, HashMap::new()),
+            comments: create_rw_signal(cx, HashMap::new()),
+            post_sequences: store_value(cx, HashMap::new()),
+            comment_sequences: store_value(cx, HashMap::new()),
+        }
+    }
+
+    /// Forgets everything, votes and saves belong to the account that made them. Responses to
+    /// requests still on their way are dropped.
+    pub fn clear(&self) {
+        self.posts.update(|p| p.clear());
+        self.comments.update(|c| c.clear());
+        self.post_sequences.update_value(|s| s.clear());
+        self.comment_sequences.update_value(|s| s.clear());
+    }
+
+    /// The current state of an item, falling back to what it was fetched with
+    pub fn state<S: ItemState>(&self, cx: Scope, id: S::Id, fetched: S) -> Memo<S> {
+        let items = S::items(self);
+        create_memo(cx, move |_| {
+            items
+                .with(|items| items.get(&id).cloned())
+                .unwrap_or_else(|| fetched.clone())
+        })
+    }
+
+    /// The latest state of an item, if it was changed this session
+    pub fn current<S: ItemState>(&self, id: S::Id) -> Option<S> {
+        S::items(self).with_untracked(|items| items.get(&id).cloned())
+    }
+
+    /// Applies `change` straight away, then sends `request` and reconciles with the server's
+    /// response. If the request fails the change is rolled back and an error is shown. When
+    /// several requests for an item are on their way, say from clicking vote quickly, only the
+    /// latest one's response or rollback is applied.
+    pub fn mutate<S, R>(
+        &self,
+        cx: Scope,
+        id: S::Id,
+        current: S,
+        change: impl FnOnce(&mut S),
+        request: R,
+    ) where
+        S: ItemState,
+        R: LemmyRequest<Response = S::Response> + Serialize + 'static,
+    {
+        let items = S::items(self);
+        let sequences = S::sequences(self);
+        let mut generation = 0;
+        sequences.update_value(|sequences| {
+            let sequence = sequences.entry(id).or_insert_with(|| Sequence {
+                latest: 0,
+                // nothing changed yet, so the server's state is what's shown
+                confirmed: items.with_untracked(|items| items.get(&id).cloned()),
+            });
+            sequence.latest += 1;
+            generation = sequence.latest;
+        });
+        // whether no request for the item has been made since this one
+        let is_latest =
+            move || sequences.with_value(|s| s.get(&id).is_some_and(|s| s.latest == generation));
+        let mut changed = current;
+        change(&mut changed);
+        items.update(|items| {
+            items.insert(id, changed);
+        });
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            match client.execute(request).await {
+                Ok(response) => {
+                    let state = S::from_response(response);
+                    sequences.update_value(|s| {
+                        if let Some(sequence) = s.get_mut(&id) {
+                            sequence.confirmed = Some(state.clone());
+                        }
+                    });
+                    // a newer request's optimistic state stays until its own response
+                    if !is_latest() {
+                        return;
+                    }
+                    S::patch_cached_feeds(id, &state);
+                    items.update(|items| {
+                        items.insert(id, state);
+                    });
+                }
+                // the change stays applied, the backend sends it once we're back online
+                Err(ClientError::QueuedOffline) => {
+                    if let Some(toasts) = use_context::<Toasts>(cx) {
+                        toasts.info(ClientError::QueuedOffline);
+                    }
+                }
+                Err(e) => {
+                    if is_latest() {
+                        let confirmed =
+                            sequences.with_value(|s| s.get(&id).and_then(|s| s.confirmed.clone()));
+                        items.update(|items| match confirmed {
+                            Some(confirmed) => {
+                                items.insert(id, confirmed);
+                            }
+                            None => {
+                                items.remove(&id);
+                            }
+                        });
+                    }
+                    if let Some(toasts) = use_context::<Toasts>(cx) {
+                        toasts.error(e);
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Feed items that can pick up changes made since they were fetched
+pub trait StoreItem {
+    fn refresh(&mut self, store: &ItemStore);
+}
+
+impl StoreItem for PostView {
+    fn refresh(&mut self, store: &ItemStore) {
+        if let Some(state) = store
+            .posts
+            .with_untracked(|p| p.get(&self.post.id).cloned())
+        {
+            state.apply(self);
+        }
+    }
+}
+
+impl StoreItem for CommentWithChildren {
+    fn refresh(&mut self, store: &ItemStore) {
+        if let Some(state) = store
+            .comments
+            .with_untracked(|c| c.get(&self.0.comment.id).cloned())
+        {
+            state.apply(&mut self.0);
+        }
+        self.1.iter_mut().for_each(|child| child.refresh(store));
+    }
+}
+
+impl StoreItem for CommunityView {
+    fn refresh(&mut self, _store: &ItemStore) {}
+}
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use capybara_lemmy_client::{
+    community::Community,
+    post::{Post, PostView},
+};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{account::Account, app::CurrentUser};
+
+/// What happens to nsfw posts and communities
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum NsfwPolicy {
+    Hide,
+    #[default]
+    Blur,
+    Show,
+}
+
+impl NsfwPolicy {
+    pub const ALL: [NsfwPolicy; 3] = [NsfwPolicy::Hide, NsfwPolicy::Blur, NsfwPolicy::Show];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NsfwPolicy::Hide => "hide",
+            NsfwPolicy::Blur => "blur",
+            NsfwPolicy::Show => "show",
+        }
+    }
+}
+
+/// Policies from most to least specific: per community, per account, then everywhere else
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NsfwSettings {
+    pub global: NsfwPolicy,
+    /// by `username@instance`
+    pub accounts: HashMap<String, NsfwPolicy>,
+    /// by the community's actor id, so it's the same whichever instance it's seen from
+    pub communities: HashMap<String, NsfwPolicy>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Nsfw {
+    pub settings: RwSignal<NsfwSettings>,
+    cx: Scope,
+}
+
+impl Nsfw {
+    pub fn new(cx: Scope, settings: NsfwSettings) -> Self {
+        Self {
+            settings: create_rw_signal(cx, settings),
+            cx,
+        }
+    }
+
+    fn account(&self) -> Option<String> {
+        let current_user = use_context::<CurrentUser>(self.cx).unwrap();
+        current_user.with(|u| u.as_ref().map(|u| format!("{}@{}", u.username, u.instance)))
+    }
+
+    /// The policy picked for the current account, None to use the global one
+    pub fn account_policy(&self) -> Option<NsfwPolicy> {
+        let account = self.account()?;
+        self.settings.with(|s| s.accounts.get(&account).copied())
+    }
+
+    pub fn set_account_policy(&self, policy: Option<NsfwPolicy>) {
+        let Some(account) = self.account() else {
+            return;
+        };
+        self.settings.update(|s| match policy {
+            Some(policy) => {
+                s.accounts.insert(account, policy);
+            }
+            None => {
+                s.accounts.remove(&account);
+            }
+        });
+    }
+
+    pub fn community_policy(&self, community: &Community) -> Option<NsfwPolicy> {
+        self.settings
+            .with(|s| s.communities.get(community.actor_id.as_str()).copied())
+    }
+
+    pub fn set_community_policy(&self, community: &Community, policy: Option<NsfwPolicy>) {
+        let key = community.actor_id.to_string();
+        self.settings.update(|s| match policy {
+            Some(policy) => {
+                s.communities.insert(key, policy);
+            }
+            None => {
+                s.communities.remove(&key);
+            }
+        });
+    }
+
+    /// What happens to nsfw content in `community`, accounts without a policy of their own hide
+    /// it when their server's `show_nsfw` is off
+    pub fn policy(&self, community: &Community) -> NsfwPolicy {
+        let account = use_context::<Account>(self.cx).unwrap();
+        self.community_policy(community)
+            .or_else(|| self.account_policy())
+            .or_else(|| {
+                (account.setting(|u| u.show_nsfw) == Some(false)).then_some(NsfwPolicy::Hide)
+            })
+            .unwrap_or_else(|| self.settings.with(|s| s.global))
+    }
+
+    /// What happens to a post, None when it isn't nsfw
+    pub fn post_policy(&self, post: &Post, community: &Community) -> Option<NsfwPolicy> {
+        (post.nsfw || community.nsfw).then(|| self.policy(community))
+    }
+
+    pub fn hides_post(&self, post: &PostView) -> bool {
+        self.post_policy(&post.post, &post.community) == Some(NsfwPolicy::Hide)
+    }
+
+    pub fn blurs_post(&self, post: &Post, community: &Community) -> bool {
+        self.post_policy(post, community) == Some(NsfwPolicy::Blur)
+    }
+
+    pub fn hides_community(&self, community: &Community) -> bool {
+        community.nsfw && self.policy(community) == NsfwPolicy::Hide
+    }
+
+    pub fn blurs_community(&self, community: &Community) -> bool {
+        community.nsfw && self.policy(community) == NsfwPolicy::Blur
+    }
+}
+
+/// A dropdown of policies, with an option for falling back to a less specific one when
+/// `inherit` names it
+#[component]
+pub fn NsfwPolicySelect<F>(
+    cx: Scope,
+    #[prop(into)] policy: Signal<Option<NsfwPolicy>>,
+    on_change: F,
+    #[prop(optional)] inherit: Option<&'static str>,
+) -> impl IntoView
+where
+    F: Fn(Option<NsfwPolicy>) + 'static,
+{
+    view! { cx,
+        <select class="bg-neutral-800 rounded" on:change=move |e| {
+            let value = event_target_value(&e);
+            on_change(NsfwPolicy::ALL.into_iter().find(|p| p.label() == value));
+        }>
+            {inherit.map(|label| view! { cx,
+                <option value="" prop:selected=move || policy().is_none()>{label}</option>
+            })}
+            {NsfwPolicy::ALL
+                .into_iter()
+                .map(|p| view! { cx,
+                    <option value=p.label() prop:selected=move || policy() == Some(p)>{p.label()}</option>
+                })
+                .collect::<Vec<_>>()}
+        </select>
+    }
+}
+
+/// The global and account policies, and every community that has its own
+#[component]
+pub fn NsfwSettingsView(cx: Scope) -> impl IntoView {
+    let nsfw = use_context::<Nsfw>(cx).unwrap();
+    let current_user = use_context::<CurrentUser>(cx).unwrap();
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"NSFW"</div>
+            <div class="text-gray-500">
+                "A community's own policy wins over the account's, which wins over the global one. "
+                "Accounts without one hide nsfw when their server settings say not to show it."
+            </div>
+            <div class="flex flex-row gap-2">
+                "everywhere:"
+                <NsfwPolicySelect
+                    policy=Signal::derive(cx, move || Some(nsfw.settings.with(|s| s.global)))
+                    on_change=move |policy: Option<NsfwPolicy>| {
+                        nsfw.settings.update(|s| s.global = policy.unwrap_or_default())
+                    }
+                />
+            </div>
+            {move || current_user.with(|u| u.is_some()).then(|| view! { cx,
+                <div class="flex flex-row gap-2">
+                    "this account:"
+                    <NsfwPolicySelect
+                        policy=Signal::derive(cx, move || nsfw.account_policy())
+                        on_change=move |policy: Option<NsfwPolicy>| nsfw.set_account_policy(policy)
+                        inherit="server setting"
+                    />
+                </div>
+            })}
+            <div class="text-xl">"Communities"</div>
+            {move || nsfw.settings.with(|s| s.communities.is_empty()).then(|| view! { cx,
+                "Set a community's policy from its badge"
+            })}
+            {move || {
+                let mut communities: Vec<(String, NsfwPolicy)> = nsfw
+                    .settings
+                    .with(|s| s.communities.clone().into_iter().collect());
+                communities.sort_by(|a, b| a.0.cmp(&b.0));
+                communities
+                    .into_iter()
+                    .map(|(actor_id, policy)| {
+                        let key = actor_id.clone();
+                        view! { cx,
+                            <div class="flex flex-row gap-2 bg-neutral-800 p-2 rounded">
+                                <div class="grow">{actor_id}</div>
+                                <div>{policy.label()}</div>
+                                <button
+                                    class="bg-neutral-700 hover:bg-neutral-500 px-1 rounded"
+                                    on:click=move |_| nsfw.settings.update(|s| {
+                                        s.communities.remove(&key);
+                                    })
+                                >
+                                    "remove"
+                                </button>
+                            </div>
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::HashMap;
+
+use capybara_lemmy_client::{
+    post::{ListingType, SortType},
+    CapyClient,
+};
+use gloo::storage::{LocalStorage, Storage};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    app::HideRead,
+    components::{
+        feed::{
+            embeds::{EmbedSettings, EmbedSettingsView, Embeds},
+            mark_read::MarkRead,
+            post_preview::ViewMode,
+            video::{Autoplay, VideoSettings, Videos},
+        },
+        image_proxy::ImageProxy,
+        sorting_components::{SortMenu, TypeMenu},
+    },
+    nsfw::{Nsfw, NsfwSettings, NsfwSettingsView},
+};
+
+/// Bumped when a change needs more than `#[serde(default)]`, with a step added to `migrate`
+const PREFERENCES_VERSION: u64 = 1;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Theme {
+    #[default]
+    System,
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Dark, Theme::Light];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Theme::System => "system",
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum FontSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+    Larger,
+}
+
+impl FontSize {
+    pub const ALL: [FontSize; 4] = [
+        FontSize::Small,
+        FontSize::Medium,
+        FontSize::Large,
+        FontSize::Larger,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FontSize::Small => "small",
+            FontSize::Medium => "medium",
+            FontSize::Large => "large",
+            FontSize::Larger => "larger",
+        }
+    }
+
+    fn percent(&self) -> u32 {
+        match self {
+            FontSize::Small => 87,
+            FontSize::Medium => 100,
+            FontSize::Large => 112,
+            FontSize::Larger => 125,
+        }
+    }
+}
+
+/// Everything the user has set up, saved by the backend with a copy in local storage
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub version: u64,
+    /// the instance used while logged out
+    pub default_instance: String,
+    /// the layout of feeds that haven't had one picked
+    pub view_mode: ViewMode,
+    /// layouts picked for feeds, by `CommunityKey::storage_key` or "front"
+    pub view_modes: HashMap<String, ViewMode>,
+    /// wins over the account's defaults on its server when set
+    pub default_sort: Option<SortType>,
+    pub default_listing_type: Option<ListingType>,
+    /// sorts picked in communities, by `CommunityKey::storage_key`
+    pub community_sorts: HashMap<String, SortType>,
+    pub hide_read: bool,
+    pub auto_mark_read: bool,
+    pub proxy_images: bool,
+    pub theme: Theme,
+    pub font_size: FontSize,
+    pub nsfw: NsfwSettings,
+    pub embeds: EmbedSettings,
+    pub video: VideoSettings,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            version: PREFERENCES_VERSION,
+            default_instance: "https://lemmy.world".to_string(),
+            view_mode: ViewMode::default(),
+            view_modes: HashMap::new(),
+            default_sort: None,
+            default_listing_type: None,
+            community_sorts: HashMap::new(),
+            hide_read: false,
+            auto_mark_read: false,
+            proxy_images: false,
+            theme: Theme::default(),
+            font_size: FontSize::default(),
+            nsfw: NsfwSettings::default(),
+            embeds: EmbedSettings::default(),
+            video: VideoSettings::default(),
+        }
+    }
+}
+
+/// Copies `saved` into `preferences` at `pointer` a field at a time, so a field that no longer
+/// parses, say after one of its enums changed, keeps its default without taking the rest with it
+fn merge_fields(preferences: &mut Value, pointer: &str, saved: Value) {
+    match saved {
+        Value::Object(fields) if preferences.pointer(pointer).is_some_and(Value::is_object) => {
+            for (key, value) in fields {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                merge_fields(preferences, &format!("{pointer}/{key}"), value);
+            }
+        }
+        saved => {
+            let Some((parent, key)) = pointer.rsplit_once('/') else {
+                return;
+            };
+            let key = key.replace("~1", "/").replace("~0", "~");
+            let Some(fields) = preferences
+                .pointer_mut(parent)
+                .and_then(Value::as_object_mut)
+            else {
+                return;
+            };
+            let previous = fields.insert(key.clone(), saved);
+            if let Err(e) = serde_json::from_value::<Preferences>(preferences.clone()) {
+                log::warn!("dropping saved preference {pointer}: {e}");
+                if let Some(fields) = preferences
+                    .pointer_mut(parent)
+                    .and_then(Value::as_object_mut)
+                {
+                    match previous {
+                        Some(previous) => fields.insert(key, previous),
+                        None => fields.remove(&key),
+                    };
+                }
+            }
+        }
+    }
+}
+
+/// Brings saved preferences up to date one version at a time. Fields added since they were
+/// saved get their defaults, so only renames and reshapes need a step here.
+fn migrate(mut preferences: Value) -> Preferences {
+    if !preferences.is_object() {
+        preferences = json!({});
+    }
+    let version = preferences["version"].as_u64().unwrap_or(0);
+    if version < 1 {
+        // before there was a store every setting had its own local storage key
+        for (key, field) in [
+            ("embed_settings", "embeds"),
+            ("nsfw_settings", "nsfw"),
+            ("video_settings", "video"),
+            ("view_modes", "view_modes"),
+            ("auto_mark_read", "auto_mark_read"),
+            ("proxy_images", "proxy_images"),
+        ] {
+            if let Ok(value) = LocalStorage::get::<Value>(key) {
+                preferences[field] = value;
+            }
+        }
+    }
+    preferences["version"] = json!(PREFERENCES_VERSION);
+    let mut merged = serde_json::to_value(Preferences::default()).unwrap();
+    merge_fields(&mut merged, "", preferences);
+    serde_json::from_value(merged).unwrap_or_default()
+}
+
+/// `saved` with the fields that were changed from `startup` to `current` while it was being read
+fn keep_changes(startup: &Preferences, current: &Preferences, saved: Preferences) -> Preferences {
+    let (Ok(Value::Object(startup)), Ok(Value::Object(current)), Ok(mut merged)) = (
+        serde_json::to_value(startup),
+        serde_json::to_value(current),
+        serde_json::to_value(&saved),
+    ) else {
+        return saved;
+    };
+    for (key, value) in current {
+        if startup.get(&key) != Some(&value) {
+            merged[key] = value;
+        }
+    }
+    serde_json::from_value(merged).unwrap_or(saved)
+}
+
+/// The preferences, and keeping them saved
+#[derive(Clone, Copy)]
+pub struct PreferenceStore {
+    pub preferences: RwSignal<Preferences>,
+    /// nothing is sent to the backend until its copy has been read, so it isn't overwritten
+    synced: StoredValue<bool>,
+    /// what local storage had, changes made since win over the backend's copy
+    startup: StoredValue<Preferences>,
+}
+
+impl PreferenceStore {
+    /// Starts from the copy in local storage, `sync` swaps in the backend's once it's read
+    pub fn new(cx: Scope) -> Self {
+        let local = migrate(LocalStorage::get::<Value>("preferences").unwrap_or(json!({})));
+        Self {
+            preferences: create_rw_signal(cx, local.clone()),
+            synced: store_value(cx, false),
+            startup: store_value(cx, local),
+        }
+    }
+
+    pub fn with<T>(&self, f: impl FnOnce(&Preferences) -> T) -> T {
+        self.preferences.with(f)
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut Preferences)) {
+        self.preferences.update(f)
+    }
+
+    /// Reads the backend's copy and saves every change after that. Without a backend the
+    /// preferences only live in local storage.
+    pub fn sync(&self, cx: Scope) {
+        let store = *self;
+        create_effect(cx, move |_| {
+            store.preferences.with(|p| {
+                LocalStorage::set("preferences", p).unwrap();
+                if store.synced.get_value() {
+                    store.save(cx, p);
+                }
+            })
+        });
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            let Ok(saved) = client.load_preferences().await else {
+                return;
+            };
+            store.synced.set_value(true);
+            match saved.and_then(|saved| serde_json::from_str(&saved).ok()) {
+                Some(saved) => {
+                    let saved = migrate(saved);
+                    let current = store.preferences.get_untracked();
+                    let merged = store
+                        .startup
+                        .with_value(|startup| keep_changes(startup, &current, saved));
+                    store.preferences.set(merged);
+                }
+                // first run with a backend, it starts from what local storage had
+                None => store.preferences.with_untracked(|p| store.save(cx, p)),
+            }
+        });
+    }
+
+    fn save(&self, cx: Scope, preferences: &Preferences) {
+        let Ok(json) = serde_json::to_string(preferences) else {
+            return;
+        };
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            if let Err(e) = client.save_preferences(&json).await {
+                log::warn!("couldn't save preferences: {e}");
+            }
+        });
+    }
+
+    /// Keeps `signal` and the preference `get` and `set` point at the same, whichever changes
+    pub fn bind<T>(
+        &self,
+        cx: Scope,
+        signal: RwSignal<T>,
+        get: fn(&Preferences) -> T,
+        set: fn(&mut Preferences, T),
+    ) where
+        T: Clone + PartialEq + 'static,
+    {
+        let preferences = self.preferences;
+        create_effect(cx, move |_| {
+            let value = signal.get();
+            if preferences.with_untracked(|p| get(p) != value) {
+                preferences.update(|p| set(p, value));
+            }
+        });
+        create_effect(cx, move |_| {
+            let value = preferences.with(get);
+            if signal.with_untracked(|v| *v != value) {
+                signal.set(value);
+            }
+        });
+    }
+
+    /// The layout picked for a feed, `key` is the community or "front" for the front page
+    pub fn view_mode(&self, key: &str) -> ViewMode {
+        self.with(|p| p.view_modes.get(key).copied().unwrap_or(p.view_mode))
+    }
+
+    pub fn set_view_mode(&self, key: &str, mode: ViewMode) {
+        self.update(|p| {
+            p.view_modes.insert(key.to_string(), mode);
+        });
+    }
+
+    /// The sort last picked in a community, `key` is its `CommunityKey::storage_key`
+    pub fn community_sort(&self, key: &str) -> Option<SortType> {
+        self.with(|p| p.community_sorts.get(key).copied())
+    }
+
+    pub fn set_community_sort(&self, key: &str, sort: Option<SortType>) {
+        self.update(|p| match sort {
+            Some(sort) => {
+                p.community_sorts.insert(key.to_string(), sort);
+            }
+            None => {
+                p.community_sorts.remove(key);
+            }
+        });
+    }
+
+    /// Applies the theme and font size to the page
+    pub fn apply_appearance(&self, cx: Scope) {
+        let store = *self;
+        create_effect(cx, move |_| {
+            let (theme, font_size) = store.with(|p| (p.theme, p.font_size));
+            let dark = match theme {
+                Theme::Dark => true,
+                Theme::Light => false,
+                Theme::System => window()
+                    .match_media("(prefers-color-scheme: dark)")
+                    .ok()
+                    .flatten()
+                    .is_some_and(|query| query.matches()),
+            };
+            let root = document().document_element().unwrap();
+            let _ = root.set_attribute("class", if dark { "dark" } else { "" });
+            let _ = root.set_attribute("style", &format!("font-size: {}%", font_size.percent()));
+        });
+    }
+}
+
+/// Creates the contexts whose state is kept in the preferences
+pub fn provide_preference_contexts(cx: Scope, store: PreferenceStore) {
+    let preferences = store.preferences.get_untracked();
+    let hide_read = HideRead(create_rw_signal(cx, preferences.hide_read));
+    store.bind(cx, hide_read.0, |p| p.hide_read, |p, v| p.hide_read = v);
+    provide_context(cx, hide_read);
+    let mark_read = MarkRead::new(cx, preferences.auto_mark_read);
+    store.bind(
+        cx,
+        mark_read.enabled,
+        |p| p.auto_mark_read,
+        |p, v| p.auto_mark_read = v,
+    );
+    provide_context(cx, mark_read);
+    let proxy = ImageProxy::new(cx, preferences.proxy_images);
+    store.bind(
+        cx,
+        proxy.enabled,
+        |p| p.proxy_images,
+        |p, v| p.proxy_images = v,
+    );
+    provide_context(cx, proxy);
+    let embeds = Embeds::new(cx, preferences.embeds);
+    store.bind(cx, embeds.0, |p| p.embeds.clone(), |p, v| p.embeds = v);
+    provide_context(cx, embeds);
+    let videos = Videos::new(cx, preferences.video);
+    store.bind(cx, videos.0, |p| p.video.clone(), |p, v| p.video = v);
+    provide_context(cx, videos);
+    let nsfw = Nsfw::new(cx, preferences.nsfw);
+    store.bind(cx, nsfw.settings, |p| p.nsfw.clone(), |p, v| p.nsfw = v);
+    provide_context(cx, nsfw);
+}
+
+/// All of the preferences in one place
+#[component]
+pub fn PreferencesView(cx: Scope) -> impl IntoView {
+    let store = use_context::<PreferenceStore>(cx).unwrap();
+    let hide_read = use_context::<HideRead>(cx).unwrap();
+    let mark_read = use_context::<MarkRead>(cx).unwrap();
+    let proxy = use_context::<ImageProxy>(cx).unwrap();
+    let videos = use_context::<Videos>(cx).unwrap();
+    let (sort, set_sort) = create_signal(cx, store.with(|p| p.default_sort));
+    let (type_, set_type) = create_signal(cx, store.with(|p| p.default_listing_type));
+    create_effect(cx, move |_| {
+        let (sort, type_) = (sort(), type_());
+        let changed = store
+            .preferences
+            .with_untracked(|p| p.default_sort != sort || p.default_listing_type != type_);
+        if changed {
+            store.update(|p| {
+                p.default_sort = sort;
+                p.default_listing_type = type_;
+            });
+        }
+    });
+    view! { cx,
+        <div class="flex flex-col gap-2">
+            <div class="text-2xl">"Settings"</div>
+            <div class="flex flex-row gap-2">
+                <label for="instance">"instance while logged out:"</label>
+                <input
+                    id="instance"
+                    class="bg-neutral-800 rounded px-1"
+                    prop:value=move || store.with(|p| p.default_instance.clone())
+                    on:change=move |e| {
+                        let instance = event_target_value(&e).trim().trim_end_matches('/').to_string();
+                        let instance = if instance.starts_with("http") { instance } else { format!("https://{instance}") };
+                        store.update(|p| p.default_instance = instance);
+                    }
+                />
+            </div>
+            <div class="flex flex-row gap-2">
+                "default layout:"
+                <select class="bg-neutral-800 rounded" on:change=move |e| {
+                    let value = event_target_value(&e);
+                    if let Some(mode) = ViewMode::ALL.into_iter().find(|m| m.label() == value) {
+                        store.update(|p| p.view_mode = mode);
+                    }
+                }>
+                    {ViewMode::ALL
+                        .into_iter()
+                        .map(|mode| view! { cx,
+                            <option value=mode.label() prop:selected=move || store.with(|p| p.view_mode == mode)>{mode.label()}</option>
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+            </div>
+            <div class="flex flex-row gap-2">
+                "default sort, None for the account's:"
+                <SortMenu sort set_sort/>
+                <TypeMenu type_ set_type/>
+            </div>
+            <div class="flex flex-row gap-2">
+                "theme:"
+                <select class="bg-neutral-800 rounded" on:change=move |e| {
+                    let value = event_target_value(&e);
+                    if let Some(theme) = Theme::ALL.into_iter().find(|t| t.label() == value) {
+                        store.update(|p| p.theme = theme);
+                    }
+                }>
+                    {Theme::ALL
+                        .into_iter()
+                        .map(|theme| view! { cx,
+                            <option value=theme.label() prop:selected=move || store.with(|p| p.theme == theme)>{theme.label()}</option>
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+                "font size:"
+                <select class="bg-neutral-800 rounded" on:change=move |e| {
+                    let value = event_target_value(&e);
+                    if let Some(size) = FontSize::ALL.into_iter().find(|s| s.label() == value) {
+                        store.update(|p| p.font_size = size);
+                    }
+                }>
+                    {FontSize::ALL
+                        .into_iter()
+                        .map(|size| view! { cx,
+                            <option value=size.label() prop:selected=move || store.with(|p| p.font_size == size)>{size.label()}</option>
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+            </div>
+            <div class="flex flex-row gap-2">
+                <label for="settings-hideread">"hide read:"</label>
+                <input type="checkbox" id="settings-hideread" prop:checked=hide_read.0 on:change=move |e| {
+                    hide_read.0.set(event_target_checked(&e));
+                }/>
+                <label for="settings-markread">"mark read on scroll:"</label>
+                <input type="checkbox" id="settings-markread" prop:checked=mark_read.enabled on:change=move |e| {
+                    mark_read.enabled.set(event_target_checked(&e));
+                }/>
+                <label for="settings-proxy">"proxy images:"</label>
+                <input type="checkbox" id="settings-proxy" prop:checked=proxy.enabled on:change=move |e| {
+                    proxy.enabled.set(event_target_checked(&e));
+                }/>
+            </div>
+            <div class="flex flex-row gap-2">
+                "autoplay videos:"
+                <select class="bg-neutral-800 rounded" on:change=move |e| {
+                    let value = event_target_value(&e);
+                    if let Some(autoplay) = Autoplay::ALL.into_iter().find(|a| a.label() == value) {
+                        videos.0.update(|s| s.autoplay = autoplay);
+                    }
+                }>
+                    {Autoplay::ALL
+                        .into_iter()
+                        .map(|autoplay| view! { cx,
+                            <option value=autoplay.label() prop:selected=move || videos.0.with(|s| s.autoplay == autoplay)>
+                                {autoplay.label()}
+                            </option>
+                        })
+                        .collect::<Vec<_>>()}
+                </select>
+            </div>
+            <NsfwSettingsView/>
+            <EmbedSettingsView/>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrate_keeps_fields_that_parse() {
+        let preferences = migrate(json!({
+            "version": 1,
+            "theme": "Purple",
+            "hide_read": true,
+            "default_instance": "https://lemmy.ml",
+        }));
+        assert_eq!(preferences.theme, Theme::default());
+        assert!(preferences.hide_read);
+        assert_eq!(preferences.default_instance, "https://lemmy.ml");
+    }
+
+    #[test]
+    fn migrate_drops_single_map_entries() {
+        let preferences = migrate(json!({
+            "version": 1,
+            "community_sorts": { "id:1": "New", "id:2": "Sideways" },
+        }));
+        assert_eq!(preferences.community_sorts.len(), 1);
+        assert_eq!(
+            preferences.community_sorts.get("id:1"),
+            Some(&SortType::New)
+        );
+    }
+
+    #[test]
+    fn migrate_defaults_garbage() {
+        assert_eq!(migrate(json!("nonsense")).version, PREFERENCES_VERSION);
+        assert_eq!(migrate(json!({ "version": 1 })), Preferences::default());
+    }
+
+    #[test]
+    fn backend_copy_keeps_changes_made_while_loading() {
+        let startup = Preferences::default();
+        let current = Preferences {
+            hide_read: true,
+            ..Preferences::default()
+        };
+        let saved = Preferences {
+            theme: Theme::Light,
+            ..Preferences::default()
+        };
+        let merged = keep_changes(&startup, &current, saved);
+        assert!(merged.hide_read);
+        assert_eq!(merged.theme, Theme::Light);
+    }
+}
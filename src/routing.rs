@@ -0,0 +1,210 @@
+use std::str::FromStr;
+
+use capybara_lemmy_client::{links::on_links, site::ResolveObject, CapyClient};
+use leptos::*;
+use leptos_router::{use_location, use_navigate, use_query_map, NavigateOptions};
+use url::Url;
+use wasm_bindgen::{prelude::*, JsCast};
+use web_sys::{Element, MouseEvent};
+
+use crate::components::toast::Toasts;
+
+#[wasm_bindgen]
+extern "C" {
+    /// Opens a url in the system's browser
+    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "shell"], js_name = open)]
+    async fn open_in_browser(url: &str) -> Result<JsValue, JsValue>;
+}
+
+/// A query parameter parsed with `FromStr`, None when it's missing or doesn't parse
+pub fn query_param<T>(cx: Scope, key: &'static str) -> Memo<Option<T>>
+where
+    T: FromStr + Clone + PartialEq + 'static,
+{
+    let query = use_query_map(cx);
+    create_memo(cx, move |_| {
+        query.with(|q| q.get(key).and_then(|value| T::from_str(value).ok()))
+    })
+}
+
+/// Sets or removes a query parameter on the current page. It's a new history entry, so back
+/// goes to the previous value. The page only means something for the sort and type it was
+/// reached with, so changing anything else starts over from the first.
+pub fn set_query_param(cx: Scope, key: &str, value: Option<String>) {
+    let mut query = use_query_map(cx).get_untracked();
+    match value {
+        Some(value) => query.insert(key.to_string(), value),
+        None => query.remove(key),
+    };
+    if key != "page" {
+        query.remove("page");
+    }
+    let path = use_location(cx).pathname.get_untracked();
+    let navigate = use_navigate(cx);
+    let _ = navigate(
+        &format!("{path}{}", query.to_query_string()),
+        NavigateOptions::default(),
+    );
+}
+
+/// Sets a query parameter in the address without telling the router, so nothing on the page is
+/// fetched again. For remembering where a feed is scrolled to when the app is reloaded.
+pub fn replace_query_param(cx: Scope, key: &str, value: Option<String>) {
+    let mut query = use_query_map(cx).get_untracked();
+    match value {
+        Some(value) => query.insert(key.to_string(), value),
+        None => query.remove(key),
+    };
+    let path = use_location(cx).pathname.get_untracked();
+    if let Ok(history) = window().history() {
+        let url = format!("{path}{}", query.to_query_string());
+        let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&url));
+    }
+}
+
+/// A signal whose writes go to a query parameter, for menus that take a `WriteSignal`
+pub fn query_writer<T>(cx: Scope, key: &'static str) -> WriteSignal<Option<T>>
+where
+    T: ToString + Clone + 'static,
+{
+    let (picked, set_picked) = create_signal(cx, None::<T>);
+    create_effect(cx, move |first: Option<()>| {
+        let value = picked();
+        if first.is_some() {
+            set_query_param(cx, key, value.map(|v| v.to_string()));
+        }
+    });
+    set_picked
+}
+
+/// Where a link to a lemmy page goes in the app, for links known to be to lemmy: ones on the
+/// instance the app is using and `lemmy://` links, which are web links without the `https`, like
+/// `lemmy://lemmy.ml/c/rust`. Communities and people are found by name, posts have ids local to
+/// their instance, so links to them only work when they're on the instance the app is using.
+pub fn app_route(link: &str, current_instance: &str) -> Option<String> {
+    let is_lemmy_link = link.starts_with("lemmy://");
+    let link = web_url(link)?;
+    let host = link.host_str()?;
+    let local = Url::parse(current_instance)
+        .ok()
+        .is_some_and(|current| current.host_str() == Some(host));
+    if !local && !is_lemmy_link {
+        return None;
+    }
+    let mut segments = link.path_segments()?.filter(|s| !s.is_empty());
+    match (segments.next()?, segments.next(), segments.next()) {
+        ("c", Some(name), None) if name.contains('@') || local => Some(format!("/c/{name}")),
+        ("c", Some(name), None) => Some(format!("/c/{name}@{host}")),
+        ("u", Some(name), None) if name.contains('@') || local => Some(format!("/person/{name}")),
+        ("u", Some(name), None) => Some(format!("/person/{name}@{host}")),
+        ("post", Some(id), None) if local && id.parse::<i32>().is_ok() => {
+            Some(format!("/post/{id}"))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a web link has the path of a lemmy page, other sites use the same paths, like
+/// youtube's `/c/` channels, so it's only a guess
+fn looks_like_lemmy(link: &str) -> bool {
+    web_url(link)
+        .and_then(|url| {
+            let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+            let page = segments.next()?;
+            let rest = segments.count();
+            Some(matches!(page, "c" | "u" | "post" | "comment") && rest == 1)
+        })
+        .unwrap_or_default()
+}
+
+/// `lemmy://` links as the web urls they stand for
+fn web_url(link: &str) -> Option<Url> {
+    let url = match link.strip_prefix("lemmy://") {
+        Some(rest) => Url::parse(&format!("https://{rest}")).ok()?,
+        None => Url::parse(link).ok()?,
+    };
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
+
+/// Like `app_route`, but links that need an id from the current instance, like posts and people
+/// elsewhere, are fetched by the account's server with `ResolveObject`
+pub async fn resolve_route(client: &CapyClient, link: &str) -> Option<String> {
+    if let Some(route) = app_route(link, &client.get_instance()) {
+        return Some(route);
+    }
+    let request = ResolveObject {
+        q: web_url(link)?.to_string(),
+        ..Default::default()
+    };
+    let resolved = client.execute(request).await.ok()?;
+    if let Some(post) = resolved.post {
+        Some(format!("/post/{}", post.post.id.0))
+    } else if let Some(comment) = resolved.comment {
+        Some(format!("/post/{}", comment.post.id.0))
+    } else if let Some(community) = resolved.community {
+        Some(format!("/c/{}", community.community.id.0))
+    } else {
+        resolved
+            .person
+            .map(|person| format!("/person/{}", person.person.id.0))
+    }
+}
+
+/// Opens the links the app was launched with, and ones it's handed while running, like from a
+/// second launch or a `lemmy://` link elsewhere. Has to be inside the router.
+#[component]
+pub fn LinkHandler(cx: Scope) -> impl IntoView {
+    let open_links = move || {
+        spawn_local(async move {
+            let client = use_context::<CapyClient>(cx).unwrap();
+            let toasts = use_context::<Toasts>(cx).unwrap();
+            let Ok(links) = client.take_links().await else {
+                return;
+            };
+            for link in links {
+                match resolve_route(&client, &link).await {
+                    Some(route) => {
+                        let navigate = use_navigate(cx);
+                        let _ = navigate(&route, NavigateOptions::default());
+                    }
+                    None => toasts.error(format!("couldn't open {link}")),
+                }
+            }
+        })
+    };
+    open_links();
+    on_links(open_links);
+}
+
+/// Opens links to lemmy pages in the app instead of the browser, for clicks in rendered html
+/// that isn't made of router links. Links that look like lemmy pages on other sites are
+/// resolved by the account's server first, and go to the browser if it doesn't know them.
+pub fn open_in_app(cx: Scope, e: MouseEvent) {
+    let Some(anchor) = e
+        .target()
+        .and_then(|t| t.dyn_into::<Element>().ok())
+        .and_then(|t| t.closest("a[href]").ok().flatten())
+    else {
+        return;
+    };
+    let href = anchor.get_attribute("href").unwrap_or_default();
+    let client = use_context::<CapyClient>(cx).unwrap();
+    if let Some(route) = app_route(&href, &client.get_instance()) {
+        e.prevent_default();
+        let navigate = use_navigate(cx);
+        let _ = navigate(&route, NavigateOptions::default());
+    } else if looks_like_lemmy(&href) {
+        e.prevent_default();
+        spawn_local(async move {
+            match resolve_route(&client, &href).await {
+                Some(route) => {
+                    let navigate = use_navigate(cx);
+                    let _ = navigate(&route, NavigateOptions::default());
+                }
+                None => {
+                    let _ = open_in_browser(&href).await;
+                }
+            }
+        });
+    }
+}
@@ -1,4 +1,6 @@
-use capybara_lemmy_client::{sensitive::Sensitive, CapyClient};
+use std::collections::HashMap;
+
+use capybara_lemmy_client::{post::PostId, sensitive::Sensitive, CapyClient};
 use gloo::storage::{LocalStorage, Storage};
 use leptos::*;
 use serde::{Deserialize, Serialize};
@@ -8,6 +10,12 @@ use crate::app::CurrentUser;
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Settings;
 
+/// Only keep collapse state for this many posts so local storage doesn't grow forever
+const MAX_COLLAPSED_POSTS: usize = 100;
+
+/// Manual collapse state by post id, most recently touched first
+type CollapsedComments = Vec<(i32, HashMap<i32, bool>)>;
+
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct LoginInfo {
     pub jwt: Sensitive<String>,
@@ -45,4 +53,62 @@ impl Settings {
         logins.retain(|l| l != &login);
         LocalStorage::set("logins", logins).unwrap();
     }
+
+    pub fn collapse_rules() -> CollapseRules {
+        LocalStorage::get("collapse_rules").ok().unwrap_or_default()
+    }
+
+    pub fn set_collapse_rules(rules: CollapseRules) {
+        LocalStorage::set("collapse_rules", rules).unwrap();
+    }
+
+    /// Comments the user manually collapsed (true) or expanded (false) on a post
+    pub fn collapsed_comments(post_id: PostId) -> HashMap<i32, bool> {
+        let posts: CollapsedComments = LocalStorage::get("collapsed_comments")
+            .ok()
+            .unwrap_or_default();
+        posts
+            .into_iter()
+            .find(|(id, _)| *id == post_id.0)
+            .map(|(_, comments)| comments)
+            .unwrap_or_default()
+    }
+
+    pub fn set_collapsed_comments(post_id: PostId, comments: HashMap<i32, bool>) {
+        let mut posts: CollapsedComments = LocalStorage::get("collapsed_comments")
+            .ok()
+            .unwrap_or_default();
+        posts.retain(|(id, _)| *id != post_id.0);
+        if !comments.is_empty() {
+            // most recently touched posts go to the front
+            posts.insert(0, (post_id.0, comments));
+        }
+        posts.truncate(MAX_COLLAPSED_POSTS);
+        LocalStorage::set("collapsed_comments", posts).unwrap();
+    }
+}
+
+/// Rules for which comments should start out collapsed
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub struct CollapseRules {
+    /// Comments with a score below this are collapsed
+    pub min_score: Option<i64>,
+    /// Collapse comments nested deeper than this
+    pub max_depth: Option<usize>,
+}
+
+impl CollapseRules {
+    pub fn should_collapse(&self, score: i64, depth: usize) -> bool {
+        self.min_score.map(|min| score < min).unwrap_or_default()
+            || self.max_depth.map(|max| depth > max).unwrap_or_default()
+    }
+}
+
+impl Default for CollapseRules {
+    fn default() -> Self {
+        Self {
+            min_score: Some(-5),
+            max_depth: None,
+        }
+    }
 }
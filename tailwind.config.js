@@ -1,6 +1,7 @@
 
 /** @type {import('tailwindcss').Config} */
 module.exports = {
+  darkMode: 'class',
   content: {
     relative: true,
     files: ["*.html", "./src/**/*.rs"],